@@ -22,8 +22,8 @@ function OrderBook() {
 
     const fetchData = async () => {
       try {
-        const response = await fetch('http://localhost:3001/api/messages');
-        const data = await response.json();
+        const response = await fetch('http://localhost:3001/api/messages?limit=20');
+        const data = (await response.json()).messages;
         setMessages(data);
 
         // Calculate stats
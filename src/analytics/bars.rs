@@ -0,0 +1,334 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::str::FromStr;
+use std::time::Duration;
+use crate::snapshot::parse_duration;
+use crate::types::msg::MboMsg;
+
+/// How trades are grouped into bars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BarSpec {
+    /// Fixed `ts_event` intervals aligned to the epoch.
+    Time(Duration),
+    /// Every N trades.
+    Tick(u32),
+    /// Close once at least this much volume traded.
+    Volume(u64),
+}
+
+impl FromStr for BarSpec {
+    type Err = String;
+
+    /// Accepts durations (`1s`, `1m`, `250ms`), `tick:<n>` or `volume:<n>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let positive = |v: &str| match v.parse::<u64>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("invalid bar spec '{}'", s)),
+        };
+        if let Some(n) = s.strip_prefix("tick:") {
+            return positive(n).and_then(|n| u32::try_from(n).map_err(|e| e.to_string())).map(BarSpec::Tick);
+        }
+        if let Some(n) = s.strip_prefix("volume:") {
+            return positive(n).map(BarSpec::Volume);
+        }
+        match parse_duration(s)? {
+            d if d.is_zero() => Err(format!("invalid bar spec '{}'", s)),
+            d => Ok(BarSpec::Time(d)),
+        }
+    }
+}
+
+impl std::fmt::Display for BarSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BarSpec::Time(d) => {
+                // The largest unit that divides the interval exactly, so it parses back.
+                let nanos = d.as_nanos();
+                let (n, unit) = [(60_000_000_000, "m"), (1_000_000_000, "s"), (1_000_000, "ms"), (1_000, "us")]
+                    .into_iter()
+                    .find(|&(per, _)| nanos.is_multiple_of(per))
+                    .map_or((nanos, "ns"), |(per, unit)| (nanos / per, unit));
+                write!(f, "{}{}", n, unit)
+            }
+            BarSpec::Tick(n) => write!(f, "tick:{}", n),
+            BarSpec::Volume(n) => write!(f, "volume:{}", n),
+        }
+    }
+}
+
+/// OHLCV bar with fixed-point prices, like DBN's `OhlcvMsg`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Bar {
+    pub instrument_id: u32,
+    pub spec: String,
+    /// `ts_event` of the bucket start (time bars) or first trade.
+    pub ts_open: u64,
+    /// `ts_event` of the last trade in the bar.
+    pub ts_close: u64,
+    pub open: i64,
+    pub high: i64,
+    pub low: i64,
+    pub close: i64,
+    pub volume: u64,
+    pub trades: u32,
+    /// False while the bar is still accumulating trades.
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct BarConfig {
+    pub specs: Vec<BarSpec>,
+    /// Completed bars kept per (instrument, spec) for HTTP queries.
+    pub history: usize,
+}
+
+impl Default for BarConfig {
+    fn default() -> Self {
+        Self {
+            specs: vec![BarSpec::Time(Duration::from_secs(1)), BarSpec::Time(Duration::from_secs(60))],
+            history: 1000,
+        }
+    }
+}
+
+struct Series {
+    current: Option<Bar>,
+    completed: VecDeque<Bar>,
+}
+
+/// Builds bars for every configured spec from trade (`T`) messages.
+/// Fills are ignored so each trade is counted once. Every message advances
+/// event time, so time bars close even when their instrument stops trading.
+pub struct BarAggregator {
+    cfg: BarConfig,
+    series: BTreeMap<(u32, BarSpec), Series>,
+    /// Earliest end of an open time bar's bucket.
+    next_time_close: Option<u64>,
+}
+
+impl BarAggregator {
+    pub fn new(cfg: BarConfig) -> Self {
+        Self { cfg, series: BTreeMap::new(), next_time_close: None }
+    }
+
+    /// Feeds one message of any kind; returns the bars completed by it.
+    pub fn on_message(&mut self, msg: &MboMsg) -> Vec<Bar> {
+        let mut done = self.advance_to(msg.ts_event());
+        if !msg.is_trade() || msg.is_undef_price() || msg.size == 0 {
+            return done;
+        }
+
+        for spec in self.cfg.specs.clone() {
+            let series = self
+                .series
+                .entry((msg.instrument_id(), spec))
+                .or_insert_with(|| Series { current: None, completed: VecDeque::new() });
+
+            let bar = series.current.get_or_insert_with(|| Bar {
+                instrument_id: msg.instrument_id(),
+                spec: spec.to_string(),
+                ts_open: match spec {
+                    BarSpec::Time(interval) => bucket_start(msg.ts_event(), interval),
+                    _ => msg.ts_event(),
+                },
+                ts_close: msg.ts_event(),
+                open: msg.price,
+                high: msg.price,
+                low: msg.price,
+                close: msg.price,
+                volume: 0,
+                trades: 0,
+                complete: false,
+            });
+            bar.high = bar.high.max(msg.price);
+            bar.low = bar.low.min(msg.price);
+            bar.close = msg.price;
+            bar.ts_close = msg.ts_event();
+            bar.volume += u64::from(msg.size);
+            bar.trades += 1;
+
+            let full = match spec {
+                BarSpec::Time(_) => false,
+                BarSpec::Tick(n) => bar.trades >= n,
+                BarSpec::Volume(v) => bar.volume >= v,
+            };
+            if full {
+                done.push(close_bar(series, self.cfg.history));
+            } else if let BarSpec::Time(interval) = spec {
+                let end = bar.ts_open.saturating_add(interval_nanos(interval));
+                self.next_time_close = Some(self.next_time_close.map_or(end, |next| next.min(end)));
+            }
+        }
+        done
+    }
+
+    /// Closes every time bar whose bucket ended at or before `ts`.
+    fn advance_to(&mut self, ts: u64) -> Vec<Bar> {
+        if self.next_time_close.is_none_or(|next| ts < next) {
+            return Vec::new();
+        }
+        let mut done = Vec::new();
+        let mut next = None;
+        for (&(_, spec), series) in self.series.iter_mut() {
+            let (BarSpec::Time(interval), Some(bar)) = (spec, series.current.as_ref()) else {
+                continue;
+            };
+            let end = bar.ts_open.saturating_add(interval_nanos(interval));
+            if end <= ts {
+                done.push(close_bar(series, self.cfg.history));
+            } else {
+                next = Some(next.map_or(end, |n: u64| n.min(end)));
+            }
+        }
+        self.next_time_close = next;
+        done
+    }
+
+    /// Completed bars (oldest first) followed by the in-progress bar, at most `limit`.
+    pub fn bars(&self, instrument_id: u32, spec: BarSpec, limit: usize) -> Option<Vec<Bar>> {
+        let series = self.series.get(&(instrument_id, spec))?;
+        let all: Vec<Bar> = series
+            .completed
+            .iter()
+            .chain(series.current.iter())
+            .cloned()
+            .collect();
+        let skip = all.len().saturating_sub(limit);
+        Some(all.into_iter().skip(skip).collect())
+    }
+
+    pub fn specs(&self) -> &[BarSpec] {
+        &self.cfg.specs
+    }
+}
+
+fn interval_nanos(interval: Duration) -> u64 {
+    (interval.as_nanos() as u64).max(1)
+}
+
+fn bucket_start(ts: u64, interval: Duration) -> u64 {
+    ts - ts % interval_nanos(interval)
+}
+
+fn close_bar(series: &mut Series, history: usize) -> Bar {
+    let mut bar = series.current.take().expect("close_bar called with an open bar");
+    bar.complete = true;
+    series.completed.push_back(bar.clone());
+    while series.completed.len() > history.max(1) {
+        series.completed.pop_front();
+    }
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::msg::{RecordHeader, ACTION_ADD, ACTION_TRADE, FLAG_LAST, SIDE_ASK, SIDE_BID};
+
+    const SEC: u64 = 1_000_000_000;
+
+    fn msg(action: std::ffi::c_char, instrument_id: u32, ts_event: u64, price: i64, size: u32) -> MboMsg {
+        MboMsg {
+            hd: RecordHeader { rtype: 0xA0, publisher_id: 1, instrument_id, ts_event },
+            order_id: 0,
+            price,
+            size,
+            flags: FLAG_LAST,
+            channel_id: 0,
+            action,
+            side: if action == ACTION_TRADE { SIDE_ASK } else { SIDE_BID },
+            ts_recv: ts_event,
+            ts_in_delta: 0,
+            sequence: 0,
+        }
+    }
+
+    fn trade(instrument_id: u32, ts_event: u64, price: i64, size: u32) -> MboMsg {
+        msg(ACTION_TRADE, instrument_id, ts_event, price, size)
+    }
+
+    fn aggregator(spec: &str) -> BarAggregator {
+        BarAggregator::new(BarConfig { specs: vec![spec.parse().unwrap()], history: 10 })
+    }
+
+    fn ohlcv(bar: &Bar) -> (i64, i64, i64, i64, u64, u32) {
+        (bar.open, bar.high, bar.low, bar.close, bar.volume, bar.trades)
+    }
+
+    #[test]
+    fn time_bars_close_at_bucket_boundaries() {
+        let mut bars = aggregator("1s");
+        assert!(bars.on_message(&trade(1, 10 * SEC + 100, 50, 2)).is_empty());
+        assert!(bars.on_message(&trade(1, 10 * SEC + 200, 53, 1)).is_empty());
+        assert!(bars.on_message(&trade(1, 11 * SEC - 1, 49, 4)).is_empty());
+
+        let done = bars.on_message(&trade(1, 11 * SEC, 51, 1));
+        assert_eq!(done.len(), 1);
+        assert_eq!(ohlcv(&done[0]), (50, 53, 49, 49, 7, 3));
+        assert_eq!((done[0].ts_open, done[0].ts_close, done[0].complete), (10 * SEC, 11 * SEC - 1, true));
+        assert_eq!(done[0].spec, "1s");
+
+        let series = bars.bars(1, "1s".parse().unwrap(), 10).unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!((series[1].ts_open, series[1].complete), (11 * SEC, false));
+    }
+
+    #[test]
+    fn time_bars_close_on_any_later_message() {
+        let mut bars = aggregator("1s");
+        bars.on_message(&trade(1, 10 * SEC, 50, 1));
+        bars.on_message(&trade(2, 10 * SEC + 5, 70, 1));
+
+        // Book updates of any instrument move event time along.
+        assert!(bars.on_message(&msg(ACTION_ADD, 3, 11 * SEC - 1, 60, 1)).is_empty());
+        let done = bars.on_message(&msg(ACTION_ADD, 3, 12 * SEC + 7, 60, 1));
+        let mut closed: Vec<(u32, u64)> = done.iter().map(|b| (b.instrument_id, b.ts_open)).collect();
+        closed.sort();
+        assert_eq!(closed, [(1, 10 * SEC), (2, 10 * SEC)]);
+        assert!(done.iter().all(|b| b.complete));
+
+        // Nothing is left open, so a later trade starts a fresh bucket without closing anything.
+        assert!(bars.on_message(&trade(1, 13 * SEC, 52, 1)).is_empty());
+        assert_eq!(bars.bars(1, "1s".parse().unwrap(), 10).unwrap().last().unwrap().ts_open, 13 * SEC);
+    }
+
+    #[test]
+    fn tick_bars_close_every_n_trades() {
+        let mut bars = aggregator("tick:3");
+        let prices = [10, 12, 11, 9, 13, 14, 8];
+        let done: Vec<Bar> =
+            prices.iter().enumerate().flat_map(|(i, &p)| bars.on_message(&trade(1, i as u64, p, 1))).collect();
+        assert_eq!(done.len(), 2);
+        assert_eq!(ohlcv(&done[0]), (10, 12, 10, 11, 3, 3));
+        assert_eq!(ohlcv(&done[1]), (9, 14, 9, 14, 3, 3));
+        assert_eq!((done[1].ts_open, done[1].ts_close), (3, 5));
+
+        // Non-trades don't count toward tick bars.
+        assert!(bars.on_message(&msg(ACTION_ADD, 1, 7, 10, 5)).is_empty());
+        let open = bars.bars(1, BarSpec::Tick(3), 10).unwrap();
+        assert_eq!((open.len(), open[2].trades, open[2].complete), (3, 1, false));
+    }
+
+    #[test]
+    fn volume_bars_close_once_the_threshold_is_reached() {
+        let mut bars = aggregator("volume:10");
+        assert!(bars.on_message(&trade(1, 1, 100, 4)).is_empty());
+        assert!(bars.on_message(&trade(1, 2, 101, 5)).is_empty());
+        // The trade that crosses the threshold stays in the bar it completes.
+        let done = bars.on_message(&trade(1, 3, 99, 6));
+        assert_eq!(done.len(), 1);
+        assert_eq!(ohlcv(&done[0]), (100, 101, 99, 99, 15, 3));
+
+        let done = bars.on_message(&trade(1, 4, 98, 10));
+        assert_eq!(ohlcv(&done[0]), (98, 98, 98, 98, 10, 1));
+    }
+
+    #[test]
+    fn time_spec_display_keeps_sub_millisecond_units() {
+        for spec in ["1m", "90s", "250ms", "1500us", "750ns", "tick:100", "volume:500"] {
+            assert_eq!(spec.parse::<BarSpec>().unwrap().to_string(), spec);
+        }
+        assert_eq!(BarSpec::Time(Duration::from_micros(500)).to_string(), "500us");
+        assert_eq!(BarSpec::Time(Duration::from_millis(120_000)).to_string(), "2m");
+    }
+}
@@ -0,0 +1,213 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::types::msg::{BidAskPair, Market, MboMsg};
+use crate::types::price::Price;
+
+/// Emitted when a book's BBO or its instrument's consolidated BBO changes.
+#[derive(Debug, Clone, Serialize)]
+pub struct BboUpdate {
+    pub ts_event: u64,
+    pub sequence: u32,
+    pub instrument_id: u32,
+    /// Publisher whose book the triggering event updated.
+    pub publisher_id: u16,
+    /// That publisher's own BBO.
+    pub book: BidAskPair,
+    /// Best bid and ask across all publishers, sizes and counts summed at the best price.
+    pub consolidated: BidAskPair,
+    /// Publisher that set the consolidated best bid.
+    pub bid_publisher: Option<u16>,
+    /// Publisher that set the consolidated best ask.
+    pub ask_publisher: Option<u16>,
+}
+
+#[derive(Default)]
+struct Consolidated {
+    quote: BidAskPair,
+    bid_publisher: Option<u16>,
+    ask_publisher: Option<u16>,
+}
+
+/// Tracks per-book and consolidated BBOs and reports changes.
+#[derive(Default)]
+pub struct BboTracker {
+    books: BTreeMap<(u32, u16), BidAskPair>,
+    instruments: BTreeMap<u32, Consolidated>,
+}
+
+impl BboTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call after `msg` has been applied to `market`. Only `F_LAST` events are
+    /// considered, so intermediate states within an event are never reported.
+    pub fn on_message(&mut self, market: &Market, msg: &MboMsg) -> Option<BboUpdate> {
+        if !msg.is_last() {
+            return None;
+        }
+        let (inst, pub_id) = (msg.instrument_id(), msg.publisher_id());
+        let books = market.books.get(&inst)?;
+        let (bid, ask) = books.get(&pub_id)?.bbo();
+        let book = BidAskPair::from_levels(bid, ask);
+        let (bid, ask) = market.aggregated_bbo(inst);
+        let quote = BidAskPair::from_levels(bid, ask);
+
+        let book_changed = self.books.get(&(inst, pub_id)) != Some(&book);
+        let prev = self.instruments.entry(inst).or_default();
+        let consolidated_changed = prev.quote != quote;
+        if !book_changed && !consolidated_changed {
+            return None;
+        }
+
+        // The setter is kept while its price stands; a new best price is credited
+        // to the publisher that just moved there, else to the first one at it.
+        let bid_at: Vec<u16> = books
+            .iter()
+            .filter(|(_, b)| quote.bid_px.is_some() && b.get_bid_level(0).map(|l| l.price) == quote.bid_px)
+            .map(|(p, _)| *p)
+            .collect();
+        let ask_at: Vec<u16> = books
+            .iter()
+            .filter(|(_, b)| quote.ask_px.is_some() && b.get_ask_level(0).map(|l| l.price) == quote.ask_px)
+            .map(|(p, _)| *p)
+            .collect();
+        let setter = |prev_px: Option<Price>, px: Option<Price>, prev: Option<u16>, at: Vec<u16>| {
+            if prev_px == px && prev.is_some_and(|p| at.contains(&p)) {
+                prev
+            } else if at.contains(&pub_id) {
+                Some(pub_id)
+            } else {
+                at.first().copied()
+            }
+        };
+        let bid_publisher = setter(prev.quote.bid_px, quote.bid_px, prev.bid_publisher, bid_at);
+        let ask_publisher = setter(prev.quote.ask_px, quote.ask_px, prev.ask_publisher, ask_at);
+
+        *prev = Consolidated { quote: quote.clone(), bid_publisher, ask_publisher };
+        self.books.insert((inst, pub_id), book.clone());
+        Some(BboUpdate {
+            ts_event: msg.ts_event(),
+            sequence: msg.sequence,
+            instrument_id: inst,
+            publisher_id: pub_id,
+            book,
+            consolidated: quote,
+            bid_publisher,
+            ask_publisher,
+        })
+    }
+}
+
+/// JSON lines file of `BboUpdate`s.
+pub struct BboFile {
+    writer: BufWriter<File>,
+}
+
+impl BboFile {
+    pub fn create(path: &Path) -> crate::Result<Self> {
+        Ok(Self { writer: BufWriter::new(File::create(path)?) })
+    }
+
+    pub fn write(&mut self, update: &BboUpdate) -> crate::Result<()> {
+        serde_json::to_writer(&mut self.writer, update)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> crate::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::c_char;
+    use crate::types::msg::{RecordHeader, ACTION_ADD, ACTION_CANCEL, FLAG_LAST, SIDE_ASK, SIDE_BID};
+
+    const BID: i64 = 100_000_000_000;
+    const BETTER_BID: i64 = 100_010_000_000;
+    const ASK: i64 = 100_020_000_000;
+
+    fn mbo(publisher_id: u16, action: c_char, side: c_char, price: i64, size: u32, order_id: u64) -> MboMsg {
+        MboMsg {
+            hd: RecordHeader { rtype: 0xA0, publisher_id, instrument_id: 1, ts_event: order_id },
+            order_id,
+            price,
+            size,
+            flags: FLAG_LAST,
+            channel_id: 0,
+            action,
+            side,
+            ts_recv: 0,
+            ts_in_delta: 0,
+            sequence: order_id as u32,
+        }
+    }
+
+    /// Applies `msg` and returns the update it caused.
+    fn step(tracker: &mut BboTracker, market: &mut Market, msg: MboMsg) -> Option<BboUpdate> {
+        market.apply(&msg).unwrap();
+        tracker.on_message(market, &msg)
+    }
+
+    fn bid(update: &BboUpdate) -> (Option<i64>, u32, u32, Option<u16>) {
+        let quote = &update.consolidated;
+        (quote.bid_px.map(Price::raw), quote.bid_sz, quote.bid_ct, update.bid_publisher)
+    }
+
+    /// Publisher 1 bids first, then publisher 2 joins at the same price.
+    fn tied() -> (BboTracker, Market) {
+        let (mut tracker, mut market) = (BboTracker::new(), Market::new());
+        step(&mut tracker, &mut market, mbo(1, ACTION_ADD, SIDE_BID, BID, 5, 1));
+        step(&mut tracker, &mut market, mbo(1, ACTION_ADD, SIDE_ASK, ASK, 5, 2));
+        step(&mut tracker, &mut market, mbo(2, ACTION_ADD, SIDE_BID, BID, 3, 3));
+        (tracker, market)
+    }
+
+    #[test]
+    fn a_tie_keeps_the_publisher_that_set_the_price() {
+        let (mut tracker, mut market) = tied();
+        let update = step(&mut tracker, &mut market, mbo(2, ACTION_ADD, SIDE_BID, BID, 1, 4)).unwrap();
+        assert_eq!(update.publisher_id, 2);
+        assert_eq!(bid(&update), (Some(BID), 9, 3, Some(1)));
+        assert_eq!((update.book.bid_sz, update.ask_publisher), (4, Some(1)));
+    }
+
+    #[test]
+    fn a_price_improvement_moves_attribution() {
+        let (mut tracker, mut market) = tied();
+        let update = step(&mut tracker, &mut market, mbo(2, ACTION_ADD, SIDE_BID, BETTER_BID, 2, 4)).unwrap();
+        assert_eq!(bid(&update), (Some(BETTER_BID), 2, 1, Some(2)));
+
+        // Publisher 1 matching the new price doesn't take it over.
+        let update = step(&mut tracker, &mut market, mbo(1, ACTION_ADD, SIDE_BID, BETTER_BID, 4, 5)).unwrap();
+        assert_eq!(bid(&update), (Some(BETTER_BID), 6, 2, Some(2)));
+    }
+
+    #[test]
+    fn the_setter_leaving_passes_attribution_to_a_publisher_that_remains() {
+        let (mut tracker, mut market) = tied();
+        let update = step(&mut tracker, &mut market, mbo(1, ACTION_CANCEL, SIDE_BID, BID, 5, 1)).unwrap();
+        assert_eq!(update.publisher_id, 1);
+        assert_eq!(update.book.bid_px, None);
+        assert_eq!(bid(&update), (Some(BID), 3, 1, Some(2)));
+
+        // And once nobody bids, nobody is credited.
+        let update = step(&mut tracker, &mut market, mbo(2, ACTION_CANCEL, SIDE_BID, BID, 3, 3)).unwrap();
+        assert_eq!(bid(&update), (None, 0, 0, None));
+        assert_eq!(update.ask_publisher, Some(1));
+    }
+
+    #[test]
+    fn unchanged_quotes_are_not_reported() {
+        let (mut tracker, mut market) = tied();
+        // Below the best bid on both the book and the consolidated quote.
+        assert!(step(&mut tracker, &mut market, mbo(2, ACTION_ADD, SIDE_BID, BID - 10_000_000, 1, 4)).is_none());
+    }
+}
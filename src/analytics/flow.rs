@@ -0,0 +1,257 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use crate::types::msg::{MboMsg, Side};
+
+/// Order lifetime bucket upper bounds in nanoseconds (1us .. 60s).
+const LIFETIME_BOUNDS_NS: [u64; 9] = [
+    1_000, 10_000, 100_000, 1_000_000, 10_000_000, 100_000_000, 1_000_000_000, 10_000_000_000,
+    60_000_000_000,
+];
+const SIZE_BOUNDS: [u64; 8] = [1, 2, 5, 10, 20, 50, 100, 500];
+
+/// Fixed-bucket histogram; `counts` has one extra overflow bucket past the last bound.
+#[derive(Debug, Clone, Serialize)]
+pub struct Histogram {
+    pub bounds: Vec<u64>,
+    pub counts: Vec<u64>,
+    pub count: u64,
+    pub sum: u64,
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+}
+
+impl Histogram {
+    pub fn new(bounds: &[u64]) -> Self {
+        Self {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len() + 1],
+            count: 0,
+            sum: 0,
+            min: None,
+            max: None,
+        }
+    }
+
+    pub fn record(&mut self, value: u64) {
+        let idx = self.bounds.partition_point(|b| *b < value);
+        self.counts[idx] += 1;
+        self.count += 1;
+        self.sum = self.sum.saturating_add(value);
+        self.min = Some(self.min.map_or(value, |m| m.min(value)));
+        self.max = Some(self.max.map_or(value, |m| m.max(value)));
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+}
+
+/// Counters and distributions for one side of one instrument.
+#[derive(Debug, Clone, Serialize)]
+pub struct SideFlow {
+    pub adds: u64,
+    pub cancels: u64,
+    pub modifies: u64,
+    /// Trades where this side was the aggressor.
+    pub trades: u64,
+    pub fills: u64,
+    pub add_size: Histogram,
+    pub trade_size: Histogram,
+    /// Add to full removal (cancel or fill), in nanoseconds of `ts_event`.
+    pub order_lifetime_ns: Histogram,
+}
+
+impl SideFlow {
+    fn new() -> Self {
+        Self {
+            adds: 0,
+            cancels: 0,
+            modifies: 0,
+            trades: 0,
+            fills: 0,
+            add_size: Histogram::new(&SIZE_BOUNDS),
+            trade_size: Histogram::new(&SIZE_BOUNDS),
+            order_lifetime_ns: Histogram::new(&LIFETIME_BOUNDS_NS),
+        }
+    }
+}
+
+/// Per-second rates over the instrument's elapsed event time.
+#[derive(Debug, Clone, Serialize)]
+pub struct SideRates {
+    pub add_rate: f64,
+    pub cancel_rate: f64,
+    pub modify_rate: f64,
+    pub trade_rate: f64,
+    pub cancel_to_trade: Option<f64>,
+    pub mean_lifetime_ns: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlowReport {
+    pub instrument_id: u32,
+    pub first_ts_event: u64,
+    pub last_ts_event: u64,
+    pub elapsed_secs: f64,
+    pub bid: SideFlow,
+    pub ask: SideFlow,
+    /// Messages without a side, mostly trades and fills whose aggressor is unknown.
+    pub unknown: SideFlow,
+    pub bid_rates: SideRates,
+    pub ask_rates: SideRates,
+    pub unknown_rates: SideRates,
+}
+
+struct InstrumentFlow {
+    first_ts: u64,
+    last_ts: u64,
+    bid: SideFlow,
+    ask: SideFlow,
+    unknown: SideFlow,
+}
+
+struct LiveOrder {
+    added_at: u64,
+    remaining: u32,
+    is_bid: bool,
+}
+
+/// Order flow statistics computed from the MBO stream.
+#[derive(Default)]
+pub struct FlowStats {
+    instruments: BTreeMap<u32, InstrumentFlow>,
+    live: HashMap<(u64, u32, u16), LiveOrder>,
+}
+
+impl FlowStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_message(&mut self, msg: &MboMsg) {
+        let inst = msg.instrument_id();
+        let ts = msg.ts_event();
+        let flow = self.instruments.entry(inst).or_insert_with(|| InstrumentFlow {
+            first_ts: ts,
+            last_ts: ts,
+            bid: SideFlow::new(),
+            ask: SideFlow::new(),
+            unknown: SideFlow::new(),
+        });
+        flow.last_ts = flow.last_ts.max(ts);
+
+        if msg.is_clear() {
+            let publisher = msg.publisher_id();
+            self.live.retain(|(_, i, p), _| !(*i == inst && *p == publisher));
+            return;
+        }
+        let side = match msg.side() {
+            Ok(Side::Bid) => &mut flow.bid,
+            Ok(Side::Ask) => &mut flow.ask,
+            Ok(Side::None) => &mut flow.unknown,
+            Err(_) => return,
+        };
+        let key = (msg.order_id, inst, msg.publisher_id());
+
+        if msg.is_add() {
+            side.adds += 1;
+            side.add_size.record(u64::from(msg.size));
+            if !msg.is_tob() {
+                self.live.insert(key, LiveOrder { added_at: ts, remaining: msg.size, is_bid: msg.is_bid() });
+            }
+        } else if msg.is_modify() {
+            side.modifies += 1;
+            if let Some(order) = self.live.get_mut(&key) {
+                order.remaining = msg.size;
+            }
+        } else if msg.is_trade() {
+            side.trades += 1;
+            side.trade_size.record(u64::from(msg.size));
+        } else if msg.is_fill() {
+            side.fills += 1;
+        } else if msg.is_cancel() {
+            side.cancels += 1;
+            if let Some(order) = self.live.get_mut(&key) {
+                order.remaining = order.remaining.saturating_sub(msg.size);
+                if order.remaining == 0 {
+                    let lifetime = ts.saturating_sub(order.added_at);
+                    let side = if order.is_bid { &mut flow.bid } else { &mut flow.ask };
+                    side.order_lifetime_ns.record(lifetime);
+                    self.live.remove(&key);
+                }
+            }
+        }
+    }
+
+    pub fn report(&self, instrument_id: u32) -> Option<FlowReport> {
+        let flow = self.instruments.get(&instrument_id)?;
+        let elapsed_secs = flow.last_ts.saturating_sub(flow.first_ts) as f64 / 1e9;
+        Some(FlowReport {
+            instrument_id,
+            first_ts_event: flow.first_ts,
+            last_ts_event: flow.last_ts,
+            elapsed_secs,
+            bid_rates: rates(&flow.bid, elapsed_secs),
+            ask_rates: rates(&flow.ask, elapsed_secs),
+            unknown_rates: rates(&flow.unknown, elapsed_secs),
+            bid: flow.bid.clone(),
+            ask: flow.ask.clone(),
+            unknown: flow.unknown.clone(),
+        })
+    }
+
+    pub fn reports(&self) -> Vec<FlowReport> {
+        self.instruments.keys().filter_map(|i| self.report(*i)).collect()
+    }
+}
+
+fn rates(side: &SideFlow, elapsed_secs: f64) -> SideRates {
+    let per_sec = |n: u64| if elapsed_secs > 0.0 { n as f64 / elapsed_secs } else { 0.0 };
+    SideRates {
+        add_rate: per_sec(side.adds),
+        cancel_rate: per_sec(side.cancels),
+        modify_rate: per_sec(side.modifies),
+        trade_rate: per_sec(side.trades),
+        cancel_to_trade: (side.trades > 0).then(|| side.cancels as f64 / side.trades as f64),
+        mean_lifetime_ns: side.order_lifetime_ns.mean(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::msg::{RecordHeader, ACTION_FILL, ACTION_TRADE, SIDE_ASK, SIDE_NONE};
+    use std::os::raw::c_char;
+
+    fn trade(action: c_char, side: c_char, size: u32, ts_event: u64) -> MboMsg {
+        MboMsg {
+            hd: RecordHeader { rtype: 0xA0, publisher_id: 1, instrument_id: 7, ts_event },
+            order_id: 0,
+            price: 1_000_000_000,
+            size,
+            flags: 0,
+            channel_id: 0,
+            action,
+            side,
+            ts_recv: ts_event,
+            ts_in_delta: 0,
+            sequence: 0,
+        }
+    }
+
+    #[test]
+    fn trades_without_aggressor_are_counted() {
+        let mut stats = FlowStats::new();
+        stats.on_message(&trade(ACTION_TRADE, SIDE_NONE, 3, 0));
+        stats.on_message(&trade(ACTION_FILL, SIDE_NONE, 3, 1));
+        stats.on_message(&trade(ACTION_TRADE, SIDE_ASK, 2, 1_000_000_000));
+
+        let report = stats.report(7).unwrap();
+        assert_eq!(report.unknown.trades, 1);
+        assert_eq!(report.unknown.fills, 1);
+        assert_eq!(report.unknown.trade_size.sum, 3);
+        assert_eq!(report.ask.trades, 1);
+        assert_eq!(report.bid.trades, 0);
+        assert_eq!(report.unknown_rates.trade_rate, 1.0);
+    }
+}
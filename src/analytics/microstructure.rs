@@ -0,0 +1,247 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use crate::types::msg::{Book, Market, MboMsg, PriceLevel};
+
+#[derive(Debug, Clone)]
+pub struct AnalyticsConfig {
+    /// Levels per side used for imbalance and depth-weighted price.
+    pub levels: usize,
+    /// Number of mid price log returns in the realized volatility window.
+    pub vol_window: usize,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self { levels: 5, vol_window: 100 }
+    }
+}
+
+/// Derived book metrics for one (instrument, publisher) book after an `F_LAST` event.
+/// Prices are in decimal units; fields are `None` while a side of the book is empty.
+#[derive(Debug, Clone, Serialize)]
+pub struct BookMetrics {
+    pub instrument_id: u32,
+    pub publisher_id: u16,
+    pub ts_event: u64,
+    pub sequence: u32,
+    pub mid: Option<f64>,
+    /// Mid weighted by the opposite side's top-of-book size.
+    pub microprice: Option<f64>,
+    pub spread: Option<f64>,
+    /// (bid size - ask size) / (bid size + ask size) over the top `levels`.
+    pub imbalance: Option<f64>,
+    /// Size weighted average price of both sides over the top `levels`.
+    pub depth_weighted_price: Option<f64>,
+    /// Volume weighted average trade price since the start of the stream.
+    pub vwap: Option<f64>,
+    pub traded_volume: u64,
+    /// sqrt of the sum of squared mid log returns over the last `vol_window` events.
+    pub realized_vol: Option<f64>,
+}
+
+#[derive(Default)]
+struct BookState {
+    notional: f64,
+    volume: u64,
+    last_mid: Option<f64>,
+    returns: VecDeque<f64>,
+}
+
+/// Maintains per-book microstructure metrics from the MBO stream.
+pub struct AnalyticsEngine {
+    cfg: AnalyticsConfig,
+    books: BTreeMap<(u32, u16), BookState>,
+}
+
+impl AnalyticsEngine {
+    pub fn new(cfg: AnalyticsConfig) -> Self {
+        Self { cfg, books: BTreeMap::new() }
+    }
+
+    /// Call after `msg` has been applied to `market`. Returns fresh metrics on `F_LAST`.
+    pub fn on_message(&mut self, market: &Market, msg: &MboMsg) -> Option<BookMetrics> {
+        let key = (msg.instrument_id(), msg.publisher_id());
+        let state = self.books.entry(key).or_default();
+
+        if let Some(price) = msg.px().filter(|_| msg.is_trade() && msg.size > 0) {
+            state.notional += price.to_f64() * f64::from(msg.size);
+            state.volume += u64::from(msg.size);
+        }
+        if !msg.is_last() {
+            return None;
+        }
+
+        let book = market.books.get(&key.0)?.get(&key.1)?;
+        let metrics = compute(book, &self.cfg, msg, state);
+
+        if let Some(mid) = metrics.mid {
+            if let Some(last) = state.last_mid.filter(|m| *m > 0.0) {
+                if mid > 0.0 {
+                    state.returns.push_back((mid / last).ln());
+                    if state.returns.len() > self.cfg.vol_window.max(1) {
+                        state.returns.pop_front();
+                    }
+                }
+            }
+            state.last_mid = Some(mid);
+        }
+
+        // Summed over the window each time: a running sum drifts off zero as returns leave it.
+        let realized_vol = (!state.returns.is_empty()).then(|| state.returns.iter().map(|r| r * r).sum::<f64>().sqrt());
+        Some(BookMetrics { realized_vol, ..metrics })
+    }
+}
+
+fn compute(book: &Book, cfg: &AnalyticsConfig, msg: &MboMsg, state: &BookState) -> BookMetrics {
+    let (bids, asks) = book.get_depth(cfg.levels.max(1));
+    let best_bid = bids.first();
+    let best_ask = asks.first();
+
+    let (mid, microprice, spread) = match (best_bid, best_ask) {
+        (Some(b), Some(a)) => {
+            let (bp, ap) = (b.price.to_f64(), a.price.to_f64());
+            let total = f64::from(b.size) + f64::from(a.size);
+            let micro = if total > 0.0 {
+                (bp * f64::from(a.size) + ap * f64::from(b.size)) / total
+            } else {
+                (bp + ap) / 2.0
+            };
+            (Some((bp + ap) / 2.0), Some(micro), Some(ap - bp))
+        }
+        _ => (None, None, None),
+    };
+
+    let side_size = |levels: &[PriceLevel]| levels.iter().map(|l| f64::from(l.size)).sum::<f64>();
+    let (bid_sz, ask_sz) = (side_size(&bids), side_size(&asks));
+    let imbalance = (bid_sz + ask_sz > 0.0).then(|| (bid_sz - ask_sz) / (bid_sz + ask_sz));
+
+    let notional: f64 = bids.iter().chain(asks.iter())
+        .map(|l| l.price.to_f64() * f64::from(l.size))
+        .sum();
+    let depth_weighted_price = (bid_sz + ask_sz > 0.0).then(|| notional / (bid_sz + ask_sz));
+
+    BookMetrics {
+        instrument_id: msg.instrument_id(),
+        publisher_id: msg.publisher_id(),
+        ts_event: msg.ts_event(),
+        sequence: msg.sequence,
+        mid,
+        microprice,
+        spread,
+        imbalance,
+        depth_weighted_price,
+        vwap: (state.volume > 0).then(|| state.notional / state.volume as f64),
+        traded_volume: state.volume,
+        realized_vol: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::msg::{RecordHeader, ACTION_ADD, ACTION_CANCEL, ACTION_TRADE, FLAG_LAST, SIDE_ASK, SIDE_BID};
+
+    fn px(units: f64) -> i64 {
+        (units * 1e9).round() as i64
+    }
+
+    fn mbo(action: std::ffi::c_char, side: std::ffi::c_char, price: f64, size: u32, order_id: u64) -> MboMsg {
+        MboMsg {
+            hd: RecordHeader { rtype: 0xA0, publisher_id: 1, instrument_id: 1, ts_event: order_id },
+            order_id,
+            price: px(price),
+            size,
+            flags: FLAG_LAST,
+            channel_id: 0,
+            action,
+            side,
+            ts_recv: 0,
+            ts_in_delta: 0,
+            sequence: order_id as u32,
+        }
+    }
+
+    fn feed(engine: &mut AnalyticsEngine, market: &mut Market, msg: MboMsg) -> Option<BookMetrics> {
+        market.apply(&msg).unwrap();
+        engine.on_message(market, &msg)
+    }
+
+    fn close(a: Option<f64>, b: f64) -> bool {
+        a.is_some_and(|a| (a - b).abs() < 1e-9)
+    }
+
+    #[test]
+    fn book_and_trade_metrics() {
+        let mut engine = AnalyticsEngine::new(AnalyticsConfig { levels: 2, vol_window: 10 });
+        let mut market = Market::new();
+        let one_sided = feed(&mut engine, &mut market, mbo(ACTION_ADD, SIDE_BID, 100.0, 3, 1)).unwrap();
+        assert_eq!((one_sided.mid, one_sided.microprice, one_sided.spread), (None, None, None));
+        assert!(close(one_sided.imbalance, 1.0));
+
+        feed(&mut engine, &mut market, mbo(ACTION_ADD, SIDE_ASK, 101.0, 1, 2));
+        feed(&mut engine, &mut market, mbo(ACTION_ADD, SIDE_BID, 99.0, 2, 3));
+        let m = feed(&mut engine, &mut market, mbo(ACTION_ADD, SIDE_ASK, 103.0, 2, 4)).unwrap();
+        assert!(close(m.mid, 100.5));
+        assert!(close(m.spread, 1.0));
+        // Weighted toward the ask, where the top-of-book size is smaller.
+        assert!(close(m.microprice, (100.0 * 1.0 + 101.0 * 3.0) / 4.0));
+        assert!(close(m.imbalance, (5.0 - 3.0) / 8.0));
+        assert!(close(m.depth_weighted_price, (300.0 + 198.0 + 101.0 + 206.0) / 8.0));
+        assert_eq!((m.vwap, m.traded_volume), (None, 0));
+
+        // A third level is outside `levels`.
+        let m = feed(&mut engine, &mut market, mbo(ACTION_ADD, SIDE_BID, 98.0, 100, 5)).unwrap();
+        assert!(close(m.imbalance, 0.25));
+
+        feed(&mut engine, &mut market, mbo(ACTION_TRADE, SIDE_ASK, 101.0, 1, 6));
+        let m = feed(&mut engine, &mut market, mbo(ACTION_TRADE, SIDE_BID, 100.0, 3, 7)).unwrap();
+        assert!(close(m.vwap, (101.0 + 300.0) / 4.0));
+        assert_eq!(m.traded_volume, 4);
+    }
+
+    #[test]
+    fn realized_vol_over_the_window() {
+        let mut engine = AnalyticsEngine::new(AnalyticsConfig { levels: 1, vol_window: 2 });
+        let mut market = Market::new();
+        feed(&mut engine, &mut market, mbo(ACTION_ADD, SIDE_BID, 99.0, 1, 1));
+        let first = feed(&mut engine, &mut market, mbo(ACTION_ADD, SIDE_ASK, 101.0, 1, 2)).unwrap();
+        assert_eq!(first.realized_vol, None);
+
+        // Mid 100 -> 100.5 -> 101 -> 100.5; only the last two returns count.
+        let mut order_id = 2;
+        let mut move_ask = |engine: &mut AnalyticsEngine, market: &mut Market, ask: f64| {
+            feed(engine, market, mbo(ACTION_CANCEL, SIDE_ASK, 0.0, 1, order_id));
+            order_id += 1;
+            feed(engine, market, mbo(ACTION_ADD, SIDE_ASK, ask, 1, order_id)).unwrap()
+        };
+        move_ask(&mut engine, &mut market, 102.0);
+        move_ask(&mut engine, &mut market, 103.0);
+        let m = move_ask(&mut engine, &mut market, 102.0);
+        let returns = [(101.0f64 / 100.5).ln(), (100.5f64 / 101.0).ln()];
+        assert!(close(m.realized_vol, returns.iter().map(|r| r * r).sum::<f64>().sqrt()));
+    }
+
+    #[test]
+    fn flat_mid_has_exactly_zero_vol() {
+        let mut engine = AnalyticsEngine::new(AnalyticsConfig { levels: 1, vol_window: 5 });
+        let mut market = Market::new();
+        feed(&mut engine, &mut market, mbo(ACTION_ADD, SIDE_BID, 64.77, 1, 1));
+        feed(&mut engine, &mut market, mbo(ACTION_ADD, SIDE_ASK, 64.83, 1, 2));
+        let mut order_id = 2;
+        let mut requote = |engine: &mut AnalyticsEngine, market: &mut Market, ask: f64| {
+            feed(engine, market, mbo(ACTION_CANCEL, SIDE_ASK, 0.0, 1, order_id));
+            order_id += 1;
+            feed(engine, market, mbo(ACTION_ADD, SIDE_ASK, ask, 1, order_id)).unwrap()
+        };
+        // Volatile returns fill the window, then leave it while the mid stays flat.
+        for ask in [64.97, 64.81, 64.87, 64.83] {
+            requote(&mut engine, &mut market, ask);
+        }
+        let mut last = None;
+        for _ in 0..20 {
+            // The cancel leaves a one-sided book without a mid, so it adds no return.
+            last = Some(requote(&mut engine, &mut market, 64.83));
+        }
+        assert_eq!(last.unwrap().realized_vol, Some(0.0));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod bars;
+pub mod bbo;
+pub mod flow;
+pub mod microstructure;
@@ -0,0 +1,255 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::str::FromStr;
+use crate::types::msg::MboMsg as C_MboMsg;
+
+/// Filters and paging for `/api/messages`. Empty values (`?instrument=`) are unset.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MessageQuery {
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub instrument: Option<u32>,
+    /// `A`, `B` or `N`.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub side: Option<char>,
+    /// `A`, `C`, `M`, `R`, `T`, `F` or `N`.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub action: Option<char>,
+    /// Only messages with a greater `sequence`.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub since_sequence: Option<u32>,
+    /// Return messages after this stream position (from a previous `next_cursor`).
+    /// Without it the newest matching messages are returned.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub cursor: Option<u64>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub limit: Option<usize>,
+}
+
+fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    match Option::<String>::deserialize(deserializer)?.as_deref() {
+        None | Some("") => Ok(None),
+        Some(value) => value.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+impl MessageQuery {
+    fn matches(&self, msg: &C_MboMsg) -> bool {
+        self.instrument.is_none_or(|i| msg.instrument_id() == i)
+            && self.side.is_none_or(|s| msg.side_char() == s)
+            && self.action.is_none_or(|a| msg.action_char() == a)
+            && self.since_sequence.is_none_or(|s| msg.sequence > s)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MessagePage<T = C_MboMsg> {
+    /// Oldest first.
+    pub messages: Vec<T>,
+    /// Pass back as `cursor` to continue after this page; `None` while the cache is empty.
+    pub next_cursor: Option<u64>,
+    /// More matching messages are already cached after `next_cursor`.
+    pub has_more: bool,
+    /// The requested cursor was older than the cache, so some messages were missed.
+    pub truncated: bool,
+}
+
+impl<T> MessagePage<T> {
+    /// Converts the messages, e.g. into `PrettyMbo`, keeping the paging fields.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> MessagePage<U> {
+        MessagePage {
+            messages: self.messages.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            has_more: self.has_more,
+            truncated: self.truncated,
+        }
+    }
+}
+
+/// Bounded ring buffer of the most recent messages. Each message gets a stream
+/// position (0, 1, 2, ...) that serves as the pagination cursor.
+pub struct MessageCache {
+    capacity: usize,
+    next: u64,
+    entries: VecDeque<C_MboMsg>,
+}
+
+pub const DEFAULT_PAGE_LIMIT: usize = 100;
+
+impl MessageCache {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self { capacity, next: 0, entries: VecDeque::with_capacity(capacity) }
+    }
+
+    pub fn push(&mut self, msg: C_MboMsg) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(msg);
+        self.next += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn first_position(&self) -> u64 {
+        self.next - self.entries.len() as u64
+    }
+
+    pub fn query(&self, query: &MessageQuery) -> MessagePage {
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, self.capacity);
+        let newest = self.next.checked_sub(1);
+        let first = self.first_position();
+
+        let Some(cursor) = query.cursor else {
+            // Tail: the newest `limit` matches.
+            let mut messages: Vec<C_MboMsg> =
+                self.entries.iter().rev().filter(|m| query.matches(m)).take(limit).copied().collect();
+            messages.reverse();
+            return MessagePage { messages, next_cursor: newest, has_more: false, truncated: false };
+        };
+
+        let start = cursor.saturating_add(1).max(first);
+        let skip = (start - first) as usize;
+        let mut messages = Vec::new();
+        let mut last = None;
+        let mut has_more = false;
+        for (pos, msg) in (start..).zip(self.entries.iter().skip(skip)) {
+            if !query.matches(msg) {
+                continue;
+            }
+            if messages.len() == limit {
+                has_more = true;
+                break;
+            }
+            messages.push(*msg);
+            last = Some(pos);
+        }
+
+        let next_cursor = if has_more { last } else { newest.map(|n| n.max(cursor)) };
+        MessagePage { messages, next_cursor, has_more, truncated: cursor.saturating_add(1) < first }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::msg::{RecordHeader, ACTION_ADD, ACTION_CANCEL, FLAG_LAST, SIDE_BID};
+
+    /// A message whose `sequence` equals its stream position.
+    fn msg(sequence: u32) -> C_MboMsg {
+        C_MboMsg {
+            hd: RecordHeader { rtype: 0xA0, publisher_id: 1, instrument_id: 1, ts_event: u64::from(sequence) },
+            order_id: u64::from(sequence),
+            price: 1_000_000_000,
+            size: 1,
+            flags: FLAG_LAST,
+            channel_id: 0,
+            action: if sequence.is_multiple_of(2) { ACTION_ADD } else { ACTION_CANCEL },
+            side: SIDE_BID,
+            ts_recv: 0,
+            ts_in_delta: 0,
+            sequence,
+        }
+    }
+
+    /// Capacity 5 after 8 pushes: positions 0..=2 are evicted, 3..=7 remain.
+    fn wrapped() -> MessageCache {
+        let mut cache = MessageCache::new(5);
+        (0..8).for_each(|i| cache.push(msg(i)));
+        cache
+    }
+
+    fn sequences(page: &MessagePage) -> Vec<u32> {
+        page.messages.iter().map(|m| m.sequence).collect()
+    }
+
+    fn after(cursor: u64, limit: usize) -> MessageQuery {
+        MessageQuery { cursor: Some(cursor), limit: Some(limit), ..MessageQuery::default() }
+    }
+
+    #[test]
+    fn empty_cache_has_no_cursor() {
+        let page = MessageCache::new(5).query(&MessageQuery::default());
+        assert!(page.messages.is_empty());
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn wraparound_keeps_the_newest() {
+        let cache = wrapped();
+        assert_eq!(cache.len(), 5);
+        let page = cache.query(&MessageQuery::default());
+        assert_eq!(sequences(&page), vec![3, 4, 5, 6, 7]);
+        assert_eq!(page.next_cursor, Some(7));
+        assert!(!page.has_more && !page.truncated);
+
+        let page = cache.query(&MessageQuery { limit: Some(2), ..MessageQuery::default() });
+        assert_eq!(sequences(&page), vec![6, 7]);
+    }
+
+    #[test]
+    fn cursor_at_eviction_boundary() {
+        let cache = wrapped();
+        // Cursor 2 was the last evicted position: nothing after it is missing.
+        let page = cache.query(&after(2, 10));
+        assert_eq!(sequences(&page), vec![3, 4, 5, 6, 7]);
+        assert!(!page.truncated);
+
+        // Cursor 1 needed position 2, which is gone.
+        let page = cache.query(&after(1, 10));
+        assert_eq!(sequences(&page), vec![3, 4, 5, 6, 7]);
+        assert!(page.truncated);
+    }
+
+    #[test]
+    fn pages_until_the_last_one() {
+        let cache = wrapped();
+        let page = cache.query(&after(3, 2));
+        assert_eq!(sequences(&page), vec![4, 5]);
+        assert_eq!(page.next_cursor, Some(5));
+        assert!(page.has_more);
+
+        let page = cache.query(&after(5, 2));
+        assert_eq!(sequences(&page), vec![6, 7]);
+        assert_eq!(page.next_cursor, Some(7));
+        assert!(!page.has_more);
+
+        let page = cache.query(&after(7, 2));
+        assert!(page.messages.is_empty());
+        assert_eq!(page.next_cursor, Some(7));
+        assert!(!page.has_more && !page.truncated);
+    }
+
+    #[test]
+    fn filtered_pages_skip_non_matches() {
+        let cache = wrapped();
+        let query = MessageQuery { action: Some('A'), ..after(2, 1) };
+        let page = cache.query(&query);
+        assert_eq!(sequences(&page), vec![4]);
+        assert!(page.has_more);
+        let page = cache.query(&MessageQuery { cursor: page.next_cursor, ..query.clone() });
+        assert_eq!(sequences(&page), vec![6]);
+        // Position 7 is a cancel, so the last page ends at the newest position.
+        assert!(!page.has_more);
+        assert_eq!(page.next_cursor, Some(7));
+    }
+
+    #[test]
+    fn cursor_ahead_of_the_stream_is_kept() {
+        let page = wrapped().query(&after(10, 5));
+        assert!(page.messages.is_empty());
+        assert_eq!(page.next_cursor, Some(10));
+    }
+}
@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::info;
+use crate::dbn::source::{MarketDataSource, SourceSpec};
+use crate::snapshot::Cadence;
+use crate::types::msg::{Market, MboMsg};
+use crate::{Error, Result};
+
+/// Bumped whenever the serialized `Market` layout changes.
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/// Full market state after `messages` records of `source`.
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint<'a> {
+    pub version: u32,
+    /// The source the state was built from, as given on the command line.
+    pub source: String,
+    /// Records consumed from the source, including rejected ones.
+    pub messages: u64,
+    /// Sequence and `ts_event` of the last record applied.
+    pub sequence: u32,
+    pub ts_event: u64,
+    /// Byte offset of the next record in the source file.
+    pub offset: Option<u64>,
+    pub market: Cow<'a, Market>,
+}
+
+impl Checkpoint<'_> {
+    pub fn load(path: &Path) -> Result<Checkpoint<'static>> {
+        let reader = BufReader::new(File::open(path)?);
+        let checkpoint: Checkpoint<'static> = serde_json::from_reader(reader)?;
+        if checkpoint.version != CHECKPOINT_VERSION {
+            return Err(Error::InvalidInput(format!(
+                "{} has checkpoint version {}, expected {}",
+                path.display(),
+                checkpoint.version,
+                CHECKPOINT_VERSION
+            )));
+        }
+        Ok(checkpoint)
+    }
+
+    /// Writes to a temporary file next to `path` and renames it over `path`, so
+    /// a crash never leaves a truncated checkpoint behind.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+// ============ RESUME ============
+
+/// A source positioned where a replay should continue, with the state to continue from.
+pub struct Resumed {
+    pub source: Box<dyn MarketDataSource>,
+    pub market: Market,
+    /// Records already covered by `market`.
+    pub messages: u64,
+}
+
+/// Opens `spec` from the start, or right after the last record covered by the
+/// checkpoint at `resume_from`, which must have been taken from the same source.
+pub fn open_source(spec: &SourceSpec, resume_from: Option<&Path>) -> Result<Resumed> {
+    let Some(path) = resume_from else {
+        return Ok(Resumed { source: spec.open()?, market: Market::new(), messages: 0 });
+    };
+    let checkpoint = Checkpoint::load(path)?;
+    let offset = checkpoint.offset.ok_or_else(|| {
+        Error::InvalidInput(format!("{} has no file offset to resume from", path.display()))
+    })?;
+    if checkpoint.source != spec.to_string() {
+        return Err(Error::InvalidInput(format!(
+            "{} was taken from {}, not {}",
+            path.display(),
+            checkpoint.source,
+            spec
+        )));
+    }
+    info!(
+        checkpoint = %path.display(),
+        messages = checkpoint.messages,
+        sequence = checkpoint.sequence,
+        ts_event = checkpoint.ts_event,
+        offset,
+        "resuming from checkpoint"
+    );
+    Ok(Resumed {
+        source: spec.open_at(offset)?,
+        market: checkpoint.market.into_owned(),
+        messages: checkpoint.messages,
+    })
+}
+
+// ============ WRITER ============
+
+#[derive(Debug, Clone)]
+pub struct CheckpointConfig {
+    pub path: PathBuf,
+    pub cadence: Cadence,
+}
+
+/// Writes checkpoints at the configured cadence. Periodic checkpoints are only
+/// taken after `F_LAST` records, so a restored book is not mid-event.
+pub struct CheckpointWriter {
+    cfg: CheckpointConfig,
+    source: String,
+    /// Messages, wall time and `ts_event` of the last checkpoint.
+    last_messages: u64,
+    last_wall: Instant,
+    last_event: Option<u64>,
+    written: u64,
+}
+
+impl CheckpointWriter {
+    /// `messages` is the record count the replay starts from.
+    pub fn new(cfg: CheckpointConfig, source: &SourceSpec, messages: u64) -> Self {
+        Self {
+            cfg,
+            source: source.to_string(),
+            last_messages: messages,
+            last_wall: Instant::now(),
+            last_event: None,
+            written: 0,
+        }
+    }
+
+    /// Call after `msg` has been applied to `market`; `messages` counts `msg` and
+    /// `offset` is the source position after it. Returns whether a checkpoint was written.
+    pub fn on_message(&mut self, market: &Market, msg: &MboMsg, messages: u64, offset: Option<u64>) -> Result<bool> {
+        if !self.is_due(msg, messages) {
+            return Ok(false);
+        }
+        self.write(market, msg, messages, offset)?;
+        Ok(true)
+    }
+
+    /// Whether a checkpoint is due after `msg`, for callers that copy the
+    /// market out of a lock before calling `write`.
+    pub fn is_due(&mut self, msg: &MboMsg, messages: u64) -> bool {
+        if !msg.is_last() {
+            return false;
+        }
+        let ts = msg.ts_event();
+        let last_event = *self.last_event.get_or_insert(ts);
+        match self.cfg.cadence {
+            Cadence::Messages(n) => messages - self.last_messages >= n.max(1) as u64,
+            Cadence::WallTime(every) => self.last_wall.elapsed() >= every,
+            Cadence::EventTime(every) => ts.saturating_sub(last_event) >= every.as_nanos() as u64,
+        }
+    }
+
+    pub fn write(&mut self, market: &Market, msg: &MboMsg, messages: u64, offset: Option<u64>) -> Result<()> {
+        Checkpoint {
+            version: CHECKPOINT_VERSION,
+            source: self.source.clone(),
+            messages,
+            sequence: msg.sequence,
+            ts_event: msg.ts_event(),
+            offset,
+            market: Cow::Borrowed(market),
+        }
+        .save(&self.cfg.path)?;
+        self.last_messages = messages;
+        self.last_wall = Instant::now();
+        self.last_event = Some(msg.ts_event());
+        self.written += 1;
+        Ok(())
+    }
+
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "CLX5_mbo.dbn";
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("hft-task-checkpoint-{}-{}.json", std::process::id(), name))
+    }
+
+    /// Applies up to `limit` records; returns how many were read and the last one.
+    fn replay(source: &mut dyn MarketDataSource, market: &mut Market, limit: u64) -> (u64, Option<MboMsg>) {
+        let (mut read, mut last) = (0, None);
+        while read < limit {
+            let Some(msg) = source.next_mbo().unwrap() else {
+                break;
+            };
+            for def in source.take_definitions() {
+                market.on_definition(&def);
+            }
+            let _ = market.apply(&msg);
+            read += 1;
+            last = Some(msg);
+        }
+        (read, last)
+    }
+
+    fn checkpoint_after(spec: &SourceSpec, messages: u64, path: &Path) {
+        let mut source = spec.open().unwrap();
+        let mut market = Market::new();
+        let (read, last) = replay(source.as_mut(), &mut market, messages);
+        assert_eq!(read, messages);
+        let cfg = CheckpointConfig { path: path.to_path_buf(), cadence: Cadence::Messages(1) };
+        CheckpointWriter::new(cfg, spec, 0)
+            .write(&market, &last.unwrap(), read, source.position())
+            .unwrap();
+    }
+
+    #[test]
+    fn resume_matches_an_uninterrupted_replay() {
+        let spec: SourceSpec = SAMPLE.parse().unwrap();
+        let mut full = Market::new();
+        let (total, _) = replay(spec.open().unwrap().as_mut(), &mut full, u64::MAX);
+        assert!(total > 20_000);
+
+        let path = temp_path("resume");
+        checkpoint_after(&spec, 20_000, &path);
+        let mut resumed = open_source(&spec, Some(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.messages, 20_000);
+        let (rest, _) = replay(resumed.source.as_mut(), &mut resumed.market, u64::MAX);
+
+        assert_eq!(resumed.messages + rest, total);
+        assert_eq!(resumed.source.position(), Some(std::fs::metadata(SAMPLE).unwrap().len()));
+        assert_eq!(serde_json::to_value(&resumed.market).unwrap(), serde_json::to_value(&full).unwrap());
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let spec: SourceSpec = SAMPLE.parse().unwrap();
+        let path = temp_path("version");
+        checkpoint_after(&spec, 10, &path);
+        let mut json: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        json["version"] = serde_json::json!(CHECKPOINT_VERSION + 1);
+        std::fs::write(&path, json.to_string()).unwrap();
+
+        let err = open_source(&spec, Some(&path)).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(err, Error::InvalidInput(ref m) if m.contains("version")), "{:?}", err);
+    }
+
+    #[test]
+    fn rejects_other_sources() {
+        let spec: SourceSpec = SAMPLE.parse().unwrap();
+        let path = temp_path("source");
+        checkpoint_after(&spec, 10, &path);
+
+        let other: SourceSpec = "other_mbo.dbn".parse().unwrap();
+        let err = open_source(&other, Some(&path)).err().unwrap();
+        let gateway: SourceSpec = "tcp://127.0.0.1:9000".parse().unwrap();
+        let gateway_err = open_source(&gateway, Some(&path)).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(err, Error::InvalidInput(ref m) if m.contains("was taken from")), "{:?}", err);
+        assert!(matches!(gateway_err, Error::InvalidInput(_)), "{:?}", gateway_err);
+    }
+}
@@ -1,255 +1,492 @@
-use dbn::decode::dbn::Decoder;
-use dbn::decode::DecodeStream;
-use dbn::record::MboMsg;
-use fallible_streaming_iterator::FallibleStreamingIterator;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::broadcast;
 use tokio::io::AsyncWriteExt;
 use std::net::SocketAddr;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::Duration;
-use std::fs::File;
-use std::io::{Write, BufWriter};
-use serde_json;
-use crate::types::msg::{RecordHeader, MboMsg as C_MboMsg, Market, BidAskPair};
-use tokio::task;
-use axum::{extract::State, routing::get, Json, Router};
-use tower_http::cors::CorsLayer;
-
-type BroadcastMsg = Vec<u8>;
-type MessageCache = Arc<Mutex<HashMap<usize, C_MboMsg>>>;
-
-#[derive(serde::Serialize)]
-struct TimedSnapshot {
-    ts_event: u64,
-    instrument_id: u32,
-    publisher_id: u16,
-    levels: Vec<BidAskPair>,
+use std::time::{Duration, Instant};
+use crate::error::Error;
+use crate::types::msg::{BookDelta, MboMsg};
+use crate::types::pretty::{MessageFormat, PrettyMbo};
+use crate::snapshot::{SnapshotConfig, SnapshotWriter};
+use crate::analytics::bars::BarConfig;
+use crate::analytics::bbo::{BboFile, BboTracker};
+use crate::analytics::microstructure::{AnalyticsConfig, AnalyticsEngine};
+use crate::dbn::feeds::{BroadcastMsg, Feeds};
+use crate::dbn::http::{start_http_server, AppState};
+use crate::dbn::metrics::Metrics;
+use crate::dbn::shutdown::{self, Shutdown};
+use crate::dbn::checkpoint::{open_source, CheckpointConfig, CheckpointWriter};
+use crate::dbn::source::{default_mbo_metadata, SourceSpec};
+use crate::dbn::recorder::{DbnRecorder, RecorderConfig};
+use tokio::task::{self, JoinSet};
+use tracing::{error, info, info_span, warn};
+
+/// Settings for `start_server`.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub addr: String,
+    pub http_addr: String,
+    pub source: SourceSpec,
+    pub sleep_time: u64,
+    pub snapshots: SnapshotConfig,
+    /// Record the broadcast stream to DBN files when set.
+    pub recorder: Option<RecorderConfig>,
+    pub analytics: AnalyticsConfig,
+    pub bars: BarConfig,
+    /// Keep lifecycles of up to this many orders for `/api/orders/{id}`.
+    pub order_history: Option<usize>,
+    /// Recent messages kept for `/api/messages`.
+    pub message_cache: usize,
+    /// Message format written to TCP clients.
+    pub tcp_format: MessageFormat,
+    /// Levels per side tracked by the `book_delta` feed.
+    pub delta_depth: usize,
+    /// Second TCP listener that streams BBO changes instead of MBO messages.
+    pub bbo_addr: Option<String>,
+    /// Also write BBO changes to this JSON lines file.
+    pub bbo_file: Option<PathBuf>,
+    /// Periodically save the full market state for fast restarts.
+    pub checkpoint: Option<CheckpointConfig>,
+    /// Start from this checkpoint instead of the start of the source.
+    pub resume_from: Option<PathBuf>,
 }
 
-/// Start TCP server and broadcast data to all connected clients
-pub async fn start_server(
-    addr: &str,
-    file_path: String,
-    sleep_time: u64,
-    snapshot_every_n: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let listener = TcpListener::bind(addr).await?;
-    println!("Server listening on {}", addr);
+type TaskResult = crate::Result<()>;
 
-    let (tx, _rx) = broadcast::channel::<BroadcastMsg>(100);
-    let cache: MessageCache = Arc::new(Mutex::new(HashMap::with_capacity(20)));
+/// How long connected clients get to drain after shutdown before they are dropped.
+const CLIENT_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often a paused reader checks for resume or shutdown.
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Start TCP server and broadcast data to all connected clients.
+/// Runs until SIGINT/SIGTERM or until a subsystem fails; the first failure is returned.
+pub async fn start_server(config: ServerConfig) -> crate::Result<()> {
+    let listener = TcpListener::bind(&config.addr)
+        .await
+        .map_err(|e| Error::bind(&config.addr, e))?;
+    info!(addr = %config.addr, "server listening");
+    let bbo_listener = match config.bbo_addr.as_deref() {
+        Some(addr) => {
+            let listener = TcpListener::bind(addr).await.map_err(|e| Error::bind(addr, e))?;
+            info!(addr, "BBO feed listening");
+            Some(listener)
+        }
+        None => None,
+    };
+
+    let state = AppState::new(&config);
+    let tcp_format = config.tcp_format;
+    let shutdown = state.shutdown.clone();
+    let mut tasks: JoinSet<(&'static str, TaskResult)> = JoinSet::new();
 
     // Rate tracking
     let message_counter = Arc::new(AtomicU64::new(0));
 
     // Start rate monitor
     let counter_clone = message_counter.clone();
-    tokio::spawn(async move {
+    let monitor_shutdown = shutdown.clone();
+    let monitor_metrics = state.metrics.clone();
+    tasks.spawn(async move {
         let mut interval = tokio::time::interval(Duration::from_secs(1));
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = monitor_shutdown.wait() => break,
+            }
             let count = counter_clone.swap(0, Ordering::Relaxed);
-            println!("📊 Server Rate: {} msg/s", count);
+            monitor_metrics.messages_per_second.set(count as i64);
+            info!(msg_per_sec = count, "server rate");
         }
+        ("rate monitor", Ok(()))
     });
 
     // Start HTTP API server
-    let cache_for_http = cache.clone();
-    tokio::spawn(async move {
-        start_http_server(cache_for_http).await;
+    let state_for_http = state.clone();
+    let http_addr = config.http_addr.clone();
+    tasks.spawn(async move {
+        let result = start_http_server(http_addr, state_for_http).await;
+        ("http server", result)
     });
 
-    let file_tx = tx.clone();
-    let cache_clone = cache.clone();
-    let counter_for_reader = message_counter.clone();
-
-    // Spawn task to read DBN file and broadcast messages
-    tokio::spawn(async move {
-        if let Err(e) = read_and_broadcast_dbn(
-            file_path,
-            file_tx,
-            cache_clone,
-            counter_for_reader,
-            sleep_time,
-            snapshot_every_n
-        )
-        .await
-        {
-            eprintln!("Error reading DBN file: {}", e);
-        }
+    // Spawn task to read the market data source and broadcast messages
+    let state_for_reader = state.clone();
+    tasks.spawn(async move {
+        ("reader", read_and_broadcast_dbn(config, state_for_reader, message_counter).await)
     });
 
     // Accept client connections
+    if let Some(bbo_listener) = bbo_listener {
+        let bbo = state.feeds.bbo.clone();
+        let state = state.clone();
+        tasks.spawn(async move { ("bbo tcp server", accept_clients(bbo_listener, state, bbo).await) });
+    }
+    let mbo = state.feeds.mbo_feed(tcp_format).clone();
+    tasks.spawn(async move { ("tcp server", accept_clients(listener, state, mbo).await) });
+
+    let signal = shutdown::signal();
+    tokio::pin!(signal);
+    let mut listening = true;
+    let mut failure: Option<Error> = None;
+
     loop {
-        match listener.accept().await {
-            Ok((socket, addr)) => {
-                let rx = tx.subscribe();
-                tokio::spawn(handle_client(socket, addr, rx));
+        tokio::select! {
+            received = &mut signal, if listening => {
+                if received.is_ok() && shutdown.is_triggered() {
+                    // A second signal skips the graceful drain.
+                    warn!("signal received during shutdown, exiting immediately");
+                    std::process::exit(130);
+                }
+                match received {
+                    Ok(name) => {
+                        info!(signal = name, "shutting down");
+                        signal.set(shutdown::signal());
+                    }
+                    Err(e) => {
+                        error!(error = %e, "failed to listen for signals, shutting down");
+                        listening = false;
+                    }
+                }
+                shutdown.trigger();
             }
-            Err(e) => {
-                eprintln!("Failed to accept connection: {}", e);
+            joined = tasks.join_next() => match joined {
+                None => break,
+                Some(Ok((task, Ok(())))) => info!(task, "task finished"),
+                Some(Ok((task, Err(e)))) => {
+                    error!(task, error = %e, "task failed, shutting down");
+                    failure.get_or_insert(e);
+                    shutdown.trigger();
+                }
+                Some(Err(e)) => {
+                    error!(error = %e, "task panicked, shutting down");
+                    failure.get_or_insert(Error::Task(e));
+                    shutdown.trigger();
+                }
             }
         }
     }
-}
 
-async fn start_http_server(cache: MessageCache) {
-    let app = Router::new()
-        .route("/api/messages", get(get_messages))
-        .layer(CorsLayer::permissive())
-        .with_state(cache);
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3001")
-        .await
-        .unwrap();
+/// Streams `feed` to every client that connects to `listener`.
+async fn accept_clients(
+    listener: TcpListener,
+    state: AppState,
+    feed: broadcast::Sender<BroadcastMsg>,
+) -> TaskResult {
+    let mut clients = JoinSet::new();
 
-    println!("HTTP API listening on http://0.0.0.0:3001");
-    axum::serve(listener, app).await.unwrap();
-}
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((socket, addr)) => {
+                    let rx = feed.subscribe();
+                    clients.spawn(handle_client(socket, addr, rx, state.metrics.clone(), state.shutdown.clone()));
+                }
+                Err(e) => {
+                    warn!(error = %e, "failed to accept connection");
+                }
+            },
+            Some(_) = clients.join_next(), if !clients.is_empty() => {}
+            _ = state.shutdown.wait() => break,
+        }
+    }
 
-async fn get_messages(State(cache): State<MessageCache>) -> Json<Vec<C_MboMsg>> {
-    let cache_guard = cache.lock().unwrap();
-    let mut messages: Vec<C_MboMsg> = cache_guard.values().cloned().collect();
-    messages.sort_by_key(|m| m.sequence);
-    Json(messages)
+    info!(clients = clients.len(), "closing client connections");
+    let drained = tokio::time::timeout(CLIENT_DRAIN_TIMEOUT, async {
+        while clients.join_next().await.is_some() {}
+    })
+    .await;
+    if drained.is_err() {
+        warn!(clients = clients.len(), "clients did not close in time, dropping them");
+        clients.shutdown().await;
+    }
+    Ok(())
 }
 
 async fn read_and_broadcast_dbn(
-    file_path: String,
-    tx: broadcast::Sender<BroadcastMsg>,
-    cache: MessageCache,
+    config: ServerConfig,
+    state: AppState,
     counter: Arc<AtomicU64>,
-    sleep_time: u64,
-    snapshot_every_n: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> TaskResult {
+    let ServerConfig {
+        source, sleep_time, snapshots, recorder, analytics, delta_depth, bbo_file, checkpoint, resume_from, ..
+    } = config;
+    let status = state.status.clone();
+    let runtime = tokio::runtime::Handle::current();
     let result = task::spawn_blocking(
-        move || -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-            let decoder = Decoder::from_file(file_path)?;
-            let mut stream = decoder.decode_stream::<MboMsg>();
-            let mut index = 0usize;
-
-            // --- create market and trackers ---
-            let mut market = Market::new();
+        move || -> TaskResult {
+            let _span = info_span!("reader", source = %source).entered();
+            let resumed = open_source(&source, resume_from.as_deref())?;
+            *state.market.lock().unwrap() = resumed.market;
+            let mut checkpoints = checkpoint.map(|c| CheckpointWriter::new(c, &source, resumed.messages));
+            let mut source = resumed.source;
+            // A network source may sit in a blocking read; shutdown closes its socket.
+            if let Some(interrupter) = source.interrupter() {
+                let shutdown = state.shutdown.clone();
+                runtime.spawn(async move {
+                    shutdown.wait().await;
+                    interrupter.interrupt();
+                });
+            }
+            info!(describe = %source.describe(), "reading market data");
+            state.status.running(source.describe());
+
+            let metadata = source
+                .metadata()
+                .cloned()
+                .unwrap_or_else(|| default_mbo_metadata(0));
+
+            // --- optional recorder of the outbound stream ---
+            let mut recorder = match recorder {
+                Some(cfg) => Some(DbnRecorder::new(cfg, metadata.clone())?),
+                None => None,
+            };
+
+            let mut bbo_file = match bbo_file {
+                Some(path) => Some(BboFile::create(&path)?),
+                None => None,
+            };
+
+            // --- snapshot feed sinks ---
+            let layout = snapshots.layout;
+            let mut snapshot_writer = SnapshotWriter::new(snapshots, metadata);
+            let mut index = resumed.messages as usize;
+            // Last applied message and the source position after it, for the final checkpoint.
+            let mut applied: Option<(MboMsg, Option<u64>)> = None;
+
+            // --- trackers (the market itself is shared through `state.market`) ---
+            let mut analytics = AnalyticsEngine::new(analytics);
+            let mut bbo = BboTracker::new();
             let mut last_inst: Option<u32> = None;
             let mut last_pub: Option<u16> = None;
 
-            // --- open snapshot feed file (JSONL) ---
-            let snapshot_file: File = File::create("snapshots.jsonl")?;
-            let mut snapshot_writer = BufWriter::new(snapshot_file);
-
-            while let Some(mbo_msg) = stream.next()? {
-                let custom_msg = C_MboMsg {
-                    hd: RecordHeader {
-                        rtype: mbo_msg.hd.rtype,
-                        publisher_id: mbo_msg.hd.publisher_id,
-                        instrument_id: mbo_msg.hd.instrument_id,
-                        ts_event: mbo_msg.hd.ts_event,
-                    },
-                    order_id: mbo_msg.order_id,
-                    price: mbo_msg.price,
-                    size: mbo_msg.size,
-                    flags: mbo_msg.flags.raw(),
-                    channel_id: mbo_msg.channel_id,
-                    action: mbo_msg.action,
-                    side: mbo_msg.side,
-                    ts_recv: mbo_msg.ts_recv,
-                    ts_in_delta: mbo_msg.ts_in_delta,
-                    sequence: mbo_msg.sequence,
-                };
-
-                // --- update order book + remember ids ---
-                market.apply(&custom_msg);
-                last_inst = Some(custom_msg.instrument_id());
-                last_pub = Some(custom_msg.publisher_id());
-
-                // --- periodic book snapshot feed ---
-                if index % snapshot_every_n == 0 {
-                    if let (Some(inst), Some(pub_id)) = (last_inst, last_pub) {
-                        if let Some(books_for_instr) = market.books.get(&inst) {
-                            if let Some(book) = books_for_instr.get(&pub_id) {
-                                // same depth as your single snapshot
-                                let levels: Vec<BidAskPair> = book.get_snapshot(50);
-
-                                let snap = TimedSnapshot {
-                                    ts_event: custom_msg.ts_event(),
-                                    instrument_id: inst,
-                                    publisher_id: pub_id,
-                                    levels,
-                                };
-
-                                serde_json::to_writer(&mut snapshot_writer, &snap)?;
-                                snapshot_writer.write_all(b"\n")?;
-                            }
+            let metrics = state.metrics.clone();
+
+            // Every consumed record counts towards throughput and is paced, applied or not.
+            let pace = || {
+                counter.fetch_add(1, Ordering::Relaxed);
+                std::thread::sleep(std::time::Duration::from_micros(sleep_time));
+            };
+
+            // Sinks are flushed below whether the stream ends, fails or is interrupted.
+            let mut stream = || -> TaskResult {
+                loop {
+                    let next = source.next_mbo();
+                    if state.shutdown.is_triggered() {
+                        info!(messages = index, "shutdown requested, stopping replay");
+                        break;
+                    }
+                    let Some(custom_msg) = next? else {
+                        break;
+                    };
+                    while state.status.is_paused() && !state.shutdown.is_triggered() {
+                        std::thread::sleep(PAUSE_POLL_INTERVAL);
+                    }
+                    if state.shutdown.is_triggered() {
+                        info!(messages = index, "shutdown requested, stopping replay");
+                        break;
+                    }
+
+                    metrics.messages_decoded.inc();
+                    metrics.replay_index.set(index as i64 + 1);
+                    metrics.replay_ts_event.set(custom_msg.ts_event() as i64);
+
+                    // --- update order book + remember ids ---
+                    // Held for the rest of this message so HTTP readers see a consistent book.
+                    let mut market = state.market.lock().unwrap();
+                    for def in source.take_definitions() {
+                        market.on_definition(&def);
+                    }
+                    let started = Instant::now();
+                    let applied_ok = market.apply(&custom_msg);
+                    metrics.book_apply_seconds.observe(started.elapsed().as_secs_f64());
+                    index += 1;
+                    applied = Some((custom_msg, source.position()));
+                    if let Err(e) = applied_ok {
+                        // Counted, but never snapshotted, aggregated, cached, broadcast or recorded.
+                        metrics.messages_rejected.inc();
+                        warn!(
+                            error = %e,
+                            instrument_id = custom_msg.instrument_id(),
+                            order_id = custom_msg.order_id,
+                            sequence = custom_msg.sequence,
+                            "rejected message"
+                        );
+                        drop(market);
+                        pace();
+                        continue;
+                    }
+
+                    // --- recording of validated records only ---
+                    if let Some(recorder) = recorder.as_mut() {
+                        recorder.record(&custom_msg)?;
+                    }
+                    last_inst = Some(custom_msg.instrument_id());
+                    last_pub = Some(custom_msg.publisher_id());
+
+                    // --- periodic book snapshot feed ---
+                    let started = Instant::now();
+                    if snapshot_writer.on_message(&market, &custom_msg)? {
+                        metrics.snapshot_write_seconds.observe(started.elapsed().as_secs_f64());
+                    }
+
+                    // --- derived metrics on complete events ---
+                    if let Some(metrics) = analytics.on_message(&market, &custom_msg) {
+                        Feeds::publish(&state.feeds.analytics, &metrics)?;
+                        let key = (metrics.instrument_id, metrics.publisher_id);
+                        state.analytics.lock().unwrap().insert(key, metrics);
+                    }
+
+                    // --- BBO changes (book and consolidated) ---
+                    if let Some(update) = bbo.on_message(&market, &custom_msg) {
+                        Feeds::publish(&state.feeds.bbo, &update)?;
+                        if let Some(file) = bbo_file.as_mut() {
+                            file.write(&update)?;
                         }
                     }
-                }
 
-                {
-                    let mut cache_guard = cache.lock().unwrap();
-                    cache_guard.insert(index % 20, custom_msg.clone());
-                }
+                    // --- level deltas, kept current even without subscribers ---
+                    if custom_msg.is_last() {
+                        let (inst, pub_id) = (custom_msg.instrument_id(), custom_msg.publisher_id());
+                        let levels = market.get_book(inst, pub_id).level_deltas(delta_depth);
+                        if !levels.is_empty() {
+                            let delta = BookDelta {
+                                ts_event: custom_msg.ts_event(),
+                                sequence: custom_msg.sequence,
+                                instrument_id: inst,
+                                publisher_id: pub_id,
+                                levels,
+                            };
+                            Feeds::publish(&state.feeds.book_delta, &delta)?;
+                        }
+                    }
+
+                    state.flow.lock().unwrap().on_message(&custom_msg);
+
+                    if let Some(orders) = state.orders.as_ref() {
+                        orders.lock().unwrap().record(&custom_msg);
+                    }
 
-                index += 1;
+                    // --- OHLCV bars from trades; every message advances time bars ---
+                    let completed = state.bars.lock().unwrap().on_message(&custom_msg);
+                    for bar in &completed {
+                        Feeds::publish(&state.feeds.bars, bar)?;
+                    }
 
-                let serialized = serde_json::to_vec(&custom_msg)?;
-                let mut message = serialized;
-                message.push(b'\n');
+                    state.cache.lock().unwrap().push(custom_msg);
 
-                let _ = tx.send(message);
+                    // Copied under the lock; serialized and synced to disk after it is released.
+                    let checkpoint = checkpoints
+                        .as_mut()
+                        .is_some_and(|writer| writer.is_due(&custom_msg, index as u64))
+                        .then(|| market.clone());
 
-                counter.fetch_add(1, Ordering::Relaxed);
-                std::thread::sleep(std::time::Duration::from_micros(sleep_time));
+                    let mut delivered = Feeds::publish(&state.feeds.mbo, &custom_msg)?;
+                    if state.feeds.mbo_pretty.receiver_count() > 0 {
+                        delivered += Feeds::publish(&state.feeds.mbo_pretty, &PrettyMbo::from(&custom_msg))?;
+                    }
+                    if delivered > 0 {
+                        metrics.messages_broadcast.inc();
+                    }
+                    drop(market);
+                    if let (Some(writer), Some(market)) = (checkpoints.as_mut(), checkpoint) {
+                        let started = Instant::now();
+                        writer.write(&market, &custom_msg, index as u64, source.position())?;
+                        info!(messages = index, elapsed_ms = started.elapsed().as_millis() as u64, "checkpoint written");
+                    }
+                    pace();
+                }
+                Ok(())
+            };
+            let streamed = stream();
+
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.finish()?;
+            }
+            if let Some(file) = bbo_file.as_mut() {
+                file.finish()?;
+            }
+            snapshot_writer.finish()?;
+
+            if let (Some(writer), Some((msg, offset))) = (checkpoints.as_mut(), applied) {
+                let market = state.market.lock().unwrap().clone();
+                writer.write(&market, &msg, index as u64, offset)?;
+                info!(messages = index, "final checkpoint written");
             }
 
             // final single snapshot.json (existing behavior)
             if let (Some(inst), Some(pub_id)) = (last_inst, last_pub) {
-                if let Err(e) =
-                    market.write_snapshot_json(inst, pub_id, 50, "snapshot.json")
-                {
-                    eprintln!("Failed to write snapshot.json: {e}");
+                let market = state.market.lock().unwrap();
+                if let Err(e) = market.write_snapshot_json(inst, pub_id, 50, layout, "snapshot.json") {
+                    warn!(error = %e, instrument_id = inst, publisher_id = pub_id, "failed to write snapshot.json");
                 }
             }
 
-            Ok(())
+            streamed
         },
     )
     .await;
 
-    match result {
-        Ok(Ok(())) => Ok(()),
-        Ok(Err(e)) => Err(e),
-        Err(e) => Err(Box::new(e)),
+    match result.map_err(Error::from).and_then(|r| r) {
+        Ok(()) => {
+            status.finished();
+            Ok(())
+        }
+        Err(e) => {
+            status.failed(e.to_string());
+            Err(e)
+        }
     }
 }
 
+#[tracing::instrument(name = "client", skip_all, fields(transport = "tcp", %addr))]
 async fn handle_client(
     mut socket: TcpStream,
     addr: SocketAddr,
     mut rx: broadcast::Receiver<BroadcastMsg>,
+    metrics: Arc<Metrics>,
+    shutdown: Shutdown,
 ) {
-    println!("New client connected: {}", addr);
+    info!("client connected");
+    let client = addr.to_string();
+    metrics.client_connected("tcp");
 
     loop {
-        match rx.recv().await {
+        let received = tokio::select! {
+            received = rx.recv() => received,
+            _ = shutdown.wait() => {
+                // Flush what was written and send FIN so the client sees a clean end of stream.
+                let _ = socket.shutdown().await;
+                break;
+            }
+        };
+        match received {
             Ok(msg) => {
                 if let Err(e) = socket.write_all(&msg).await {
-                    eprintln!("Failed to send to {}: {}", addr, e);
+                    warn!(error = %e, "failed to send to client");
                     break;
                 }
+                metrics.client_sent("tcp", &client, msg.len());
             }
             Err(broadcast::error::RecvError::Lagged(skipped)) => {
-                eprintln!("Client {} lagged, skipped {} messages", addr, skipped);
+                warn!(skipped, "client lagged");
+                metrics.client_lagged("tcp", &client, skipped);
                 continue;
             }
             Err(broadcast::error::RecvError::Closed) => {
-                println!("Broadcast channel closed");
+                info!("broadcast channel closed");
                 break;
             }
         }
     }
 
-    println!("Client disconnected: {}", addr);
+    metrics.client_disconnected("tcp", &client);
+    info!("client disconnected");
 }
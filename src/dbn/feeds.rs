@@ -0,0 +1,77 @@
+use tokio::sync::broadcast;
+use crate::types::pretty::MessageFormat;
+
+/// Newline terminated JSON, shared by the TCP and WebSocket transports.
+pub type BroadcastMsg = Vec<u8>;
+
+const FEED_CAPACITY: usize = 100;
+
+/// Broadcast channels for every stream the server publishes.
+#[derive(Clone)]
+pub struct Feeds {
+    /// Raw MBO messages (the original TCP feed).
+    pub mbo: broadcast::Sender<BroadcastMsg>,
+    /// The same messages as `PrettyMbo`, only serialized while someone listens.
+    pub mbo_pretty: broadcast::Sender<BroadcastMsg>,
+    /// `BookMetrics` after every `F_LAST` event.
+    pub analytics: broadcast::Sender<BroadcastMsg>,
+    /// Completed OHLCV bars.
+    pub bars: broadcast::Sender<BroadcastMsg>,
+    /// `BookDelta` level changes after every `F_LAST` event.
+    pub book_delta: broadcast::Sender<BroadcastMsg>,
+    /// `BboUpdate` whenever a book's or the consolidated BBO changes.
+    pub bbo: broadcast::Sender<BroadcastMsg>,
+}
+
+impl Feeds {
+    pub fn new() -> Self {
+        Self {
+            mbo: broadcast::channel(FEED_CAPACITY).0,
+            mbo_pretty: broadcast::channel(FEED_CAPACITY).0,
+            analytics: broadcast::channel(FEED_CAPACITY).0,
+            bars: broadcast::channel(FEED_CAPACITY).0,
+            book_delta: broadcast::channel(FEED_CAPACITY).0,
+            bbo: broadcast::channel(FEED_CAPACITY).0,
+        }
+    }
+
+    /// Looks a channel up by the name used in `/ws/{channel}`.
+    pub fn channel(&self, name: &str) -> Option<&broadcast::Sender<BroadcastMsg>> {
+        match name {
+            "mbo" => Some(&self.mbo),
+            "mbo_pretty" => Some(&self.mbo_pretty),
+            "analytics" => Some(&self.analytics),
+            "bars" => Some(&self.bars),
+            "book_delta" => Some(&self.book_delta),
+            "bbo" => Some(&self.bbo),
+            _ => None,
+        }
+    }
+
+    pub fn mbo_feed(&self, format: MessageFormat) -> &broadcast::Sender<BroadcastMsg> {
+        match format {
+            MessageFormat::Compact => &self.mbo,
+            MessageFormat::Pretty => &self.mbo_pretty,
+        }
+    }
+
+    /// Serializes `value` as one JSON line and sends it if anyone is listening.
+    /// Returns the number of receivers it was sent to.
+    pub fn publish<T: serde::Serialize>(
+        tx: &broadcast::Sender<BroadcastMsg>,
+        value: &T,
+    ) -> Result<usize, serde_json::Error> {
+        if tx.receiver_count() == 0 {
+            return Ok(0);
+        }
+        let mut message = serde_json::to_vec(value)?;
+        message.push(b'\n');
+        Ok(tx.send(message).unwrap_or(0))
+    }
+}
+
+impl Default for Feeds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,70 @@
+use dbn::decode::dbn::Decoder;
+use dbn::decode::{DbnMetadata, DecodeRecordRef};
+use dbn::encode::dbn::Encoder;
+use dbn::encode::{EncodeRecord, EncodeRecordRef};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task;
+use tracing::{info, info_span, warn};
+use crate::dbn::source::SourceError;
+
+/// Local stand-in for a live market data gateway: every client that connects
+/// receives the DBN file as a paced DBN byte stream (metadata, then records).
+pub async fn start_gateway(
+    addr: &str,
+    file_path: PathBuf,
+    sleep_time: u64,
+) -> Result<(), SourceError> {
+    let listener = TcpListener::bind(addr).await.map_err(|e| SourceError::bind(addr, e))?;
+    info!(%addr, file = %file_path.display(), "mock gateway listening");
+
+    loop {
+        match listener.accept().await {
+            Ok((socket, peer)) => {
+                let socket = socket.into_std()?;
+                socket.set_nonblocking(false)?;
+                let path = file_path.clone();
+                task::spawn_blocking(move || {
+                    let _span = info_span!("gateway_client", %peer).entered();
+                    if let Err(e) = stream_file(socket, path, sleep_time) {
+                        warn!(error = %e, "gateway stream ended");
+                    }
+                });
+            }
+            Err(e) => {
+                warn!(error = %e, "gateway failed to accept connection");
+            }
+        }
+    }
+}
+
+fn stream_file(
+    socket: std::net::TcpStream,
+    path: PathBuf,
+    sleep_time: u64,
+) -> Result<(), SourceError> {
+    info!("gateway client connected");
+    socket.set_nodelay(true)?;
+
+    let mut decoder = Decoder::new(BufReader::new(File::open(&path)?))?;
+    let metadata = decoder.metadata().clone();
+    let mut encoder = Encoder::new(BufWriter::new(socket), &metadata)?;
+
+    let mut sent = 0u64;
+    // Definitions and other schemas pass through; the client picks what it needs.
+    while let Some(record) = decoder.decode_record_ref()? {
+        encoder.encode_record_ref(record)?;
+        sent += 1;
+        if sleep_time > 0 {
+            encoder.flush()?;
+            std::thread::sleep(Duration::from_micros(sleep_time));
+        }
+    }
+    encoder.flush()?;
+
+    info!(records = sent, "gateway finished streaming");
+    Ok(())
+}
@@ -0,0 +1,472 @@
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{routing::{get, post}, Json, Router};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tower_http::cors::CorsLayer;
+use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
+use tracing::{info, warn, Level};
+use crate::analytics::bars::{Bar, BarAggregator, BarSpec};
+use crate::analytics::flow::{FlowReport, FlowStats};
+use crate::analytics::microstructure::BookMetrics;
+use crate::dbn::cache::{MessageCache, MessageQuery};
+use crate::dbn::dbn_local::ServerConfig;
+use crate::dbn::feeds::{BroadcastMsg, Feeds};
+use crate::dbn::metrics::Metrics;
+use crate::dbn::shutdown::Shutdown;
+use crate::dbn::status::{ReplayStatus, StatusReport};
+use crate::error::Error;
+use crate::types::history::{OrderHistory, OrderHistoryStore};
+use crate::types::msg::{L3Snapshot, Market};
+use crate::types::pretty::{MessageFormat, PrettyMbo};
+
+pub type MessageStore = Arc<Mutex<MessageCache>>;
+pub type MarketStore = Arc<Mutex<Market>>;
+pub type AnalyticsStore = Arc<Mutex<BTreeMap<(u32, u16), BookMetrics>>>;
+pub type BarStore = Arc<Mutex<BarAggregator>>;
+pub type OrderStore = Arc<Mutex<OrderHistoryStore>>;
+pub type FlowStore = Arc<Mutex<FlowStats>>;
+
+/// State shared between the reader task and the HTTP handlers.
+#[derive(Clone)]
+pub struct AppState {
+    pub cache: MessageStore,
+    /// The live order books, updated by the reader one message at a time.
+    pub market: MarketStore,
+    pub analytics: AnalyticsStore,
+    pub bars: BarStore,
+    /// Only present when order history is enabled.
+    pub orders: Option<OrderStore>,
+    pub flow: FlowStore,
+    pub feeds: Feeds,
+    pub metrics: Arc<Metrics>,
+    pub shutdown: Shutdown,
+    pub status: Arc<ReplayStatus>,
+}
+
+impl AppState {
+    pub fn new(config: &ServerConfig) -> Self {
+        Self {
+            cache: Arc::new(Mutex::new(MessageCache::new(config.message_cache))),
+            market: Arc::new(Mutex::new(Market::new())),
+            analytics: Arc::new(Mutex::new(BTreeMap::new())),
+            bars: Arc::new(Mutex::new(BarAggregator::new(config.bars.clone()))),
+            orders: config.order_history.map(|cap| Arc::new(Mutex::new(OrderHistoryStore::new(cap)))),
+            flow: Arc::new(Mutex::new(FlowStats::new())),
+            feeds: Feeds::new(),
+            metrics: Arc::new(Metrics::new()),
+            shutdown: Shutdown::new(),
+            status: Arc::new(ReplayStatus::new(config.source.to_string())),
+        }
+    }
+}
+
+/// Serves until `state.shutdown` is triggered; bind and serve errors are returned.
+pub async fn start_http_server(addr: String, state: AppState) -> crate::Result<()> {
+    let shutdown = state.shutdown.clone();
+    let app = router(state);
+
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .map_err(|e| Error::bind(&addr, e))?;
+
+    info!("HTTP API listening on http://{}", addr);
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move { shutdown.wait().await })
+        .await?;
+    info!("HTTP API stopped");
+    Ok(())
+}
+
+fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/ready", get(ready))
+        .route("/api/status", get(get_status))
+        .route("/api/replay/pause", post(pause_replay))
+        .route("/api/replay/resume", post(resume_replay))
+        .route("/api/messages", get(get_messages))
+        .route("/api/analytics", get(get_analytics))
+        .route("/api/analytics/:instrument_id", get(get_instrument_analytics))
+        .route("/api/book/:instrument_id/l3", get(get_l3_book))
+        .route("/api/bars/:instrument_id", get(get_bars))
+        .route("/api/orders/:order_id", get(get_order_history))
+        .route("/api/stats", get(get_stats))
+        .route("/api/stats/:instrument_id", get(get_instrument_stats))
+        .route("/metrics", get(get_metrics))
+        .route("/ws/:channel", get(ws_channel))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .layer(CorsLayer::permissive())
+        .with_state(state)
+}
+
+/// Lookup misses (including disabled features) become 404 and bad arguments 400;
+/// everything else is a 500.
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            Error::NotFound(_) | Error::Disabled { .. } => StatusCode::NOT_FOUND,
+            Error::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// Liveness: the process is up and serving HTTP.
+async fn health() -> &'static str {
+    "ok"
+}
+
+/// Readiness: the source is open and the replay has not failed.
+async fn ready(State(state): State<AppState>) -> (StatusCode, &'static str) {
+    if state.status.is_ready() {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "not ready")
+    }
+}
+
+async fn get_status(State(state): State<AppState>) -> Json<StatusReport> {
+    let (status, metrics) = (&state.status, &state.metrics);
+    let index = metrics.replay_index.get().max(0) as u64;
+    Json(StatusReport {
+        state: status.state(),
+        source: status.source(),
+        error: status.error(),
+        index,
+        ts_event: (index > 0).then(|| metrics.replay_ts_event.get() as u64),
+        msg_per_sec: metrics.messages_per_second.get().max(0) as u64,
+        tcp_clients: metrics.connected("tcp"),
+        ws_clients: metrics.connected("ws"),
+        uptime_secs: status.uptime().as_secs_f64(),
+    })
+}
+
+async fn pause_replay(State(state): State<AppState>) -> Json<StatusReport> {
+    state.status.set_paused(true);
+    get_status(State(state)).await
+}
+
+async fn resume_replay(State(state): State<AppState>) -> Json<StatusReport> {
+    state.status.set_paused(false);
+    get_status(State(state)).await
+}
+
+async fn get_messages(
+    Query(query): Query<MessageQuery>,
+    Query(format): Query<FormatQuery>,
+    State(state): State<AppState>,
+) -> Response {
+    let page = state.cache.lock().unwrap().query(&query);
+    match format.format {
+        MessageFormat::Compact => Json(page).into_response(),
+        MessageFormat::Pretty => Json(page.map(|m| PrettyMbo::from(&m))).into_response(),
+    }
+}
+
+async fn get_analytics(State(state): State<AppState>) -> Json<Vec<BookMetrics>> {
+    let store = state.analytics.lock().unwrap();
+    Json(store.values().cloned().collect())
+}
+
+async fn get_instrument_analytics(
+    Path(instrument_id): Path<u32>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<BookMetrics>>, Error> {
+    let store = state.analytics.lock().unwrap();
+    let metrics: Vec<BookMetrics> = store
+        .range((instrument_id, u16::MIN)..=(instrument_id, u16::MAX))
+        .map(|(_, m)| m.clone())
+        .collect();
+    if metrics.is_empty() {
+        return Err(Error::NotFound(format!("analytics for instrument {}", instrument_id)));
+    }
+    Ok(Json(metrics))
+}
+
+#[derive(serde::Deserialize)]
+struct BarQuery {
+    /// Defaults to the first configured spec.
+    spec: Option<String>,
+    limit: Option<usize>,
+}
+
+async fn get_bars(
+    Path(instrument_id): Path<u32>,
+    Query(query): Query<BarQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Bar>>, Error> {
+    let bars = state.bars.lock().unwrap();
+    let spec = match query.spec {
+        Some(s) => s.parse::<BarSpec>().map_err(Error::InvalidInput)?,
+        None => *bars
+            .specs()
+            .first()
+            .ok_or_else(|| Error::NotFound("bar spec".to_string()))?,
+    };
+    bars.bars(instrument_id, spec, query.limit.unwrap_or(500))
+        .map(Json)
+        .ok_or_else(|| Error::NotFound(format!("{} bars for instrument {}", spec, instrument_id)))
+}
+
+#[derive(serde::Deserialize)]
+struct L3Query {
+    /// Only this publisher's book; all publishers of the instrument otherwise.
+    publisher: Option<u16>,
+    /// Best N levels per side; the whole book otherwise.
+    levels: Option<usize>,
+}
+
+async fn get_l3_book(
+    Path(instrument_id): Path<u32>,
+    Query(query): Query<L3Query>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<L3Snapshot>>, Error> {
+    let market = state.market.lock().unwrap();
+    let books = market
+        .books
+        .get(&instrument_id)
+        .ok_or_else(|| Error::NotFound(format!("book for instrument {}", instrument_id)))?;
+    let snapshots: Vec<L3Snapshot> = books
+        .keys()
+        .filter(|pub_id| query.publisher.is_none_or(|p| p == **pub_id))
+        .filter_map(|pub_id| market.get_l3_snapshot(instrument_id, *pub_id, query.levels))
+        .collect();
+    if snapshots.is_empty() {
+        return Err(Error::NotFound(format!(
+            "book for publisher {} of instrument {}",
+            query.publisher.unwrap_or_default(),
+            instrument_id
+        )));
+    }
+    Ok(Json(snapshots))
+}
+
+async fn get_order_history(
+    Path(order_id): Path<u64>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<OrderHistory>>, Error> {
+    let Some(orders) = state.orders.as_ref() else {
+        return Err(Error::Disabled { feature: "order history", flag: "--order-history" });
+    };
+    let history = orders.lock().unwrap().get(order_id);
+    if history.is_empty() {
+        // Never seen, or already evicted from the bounded store.
+        return Err(Error::NotFound(format!("order {}", order_id)));
+    }
+    Ok(Json(history))
+}
+
+async fn get_stats(State(state): State<AppState>) -> Json<Vec<FlowReport>> {
+    Json(state.flow.lock().unwrap().reports())
+}
+
+async fn get_instrument_stats(
+    Path(instrument_id): Path<u32>,
+    State(state): State<AppState>,
+) -> Result<Json<FlowReport>, Error> {
+    state
+        .flow
+        .lock()
+        .unwrap()
+        .report(instrument_id)
+        .map(Json)
+        .ok_or_else(|| Error::NotFound(format!("order flow for instrument {}", instrument_id)))
+}
+
+async fn get_metrics(State(state): State<AppState>) -> Result<String, Error> {
+    state.metrics.render()
+}
+
+#[derive(serde::Deserialize)]
+struct FormatQuery {
+    #[serde(default)]
+    format: MessageFormat,
+}
+
+async fn ws_channel(
+    Path(channel): Path<String>,
+    Query(query): Query<FormatQuery>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+) -> Response {
+    let tx = match channel.as_str() {
+        "mbo" => Some(state.feeds.mbo_feed(query.format)),
+        name => state.feeds.channel(name),
+    };
+    let Some(tx) = tx else {
+        return (StatusCode::NOT_FOUND, format!("unknown channel '{}'", channel)).into_response();
+    };
+    let rx = tx.subscribe();
+    let metrics = state.metrics.clone();
+    let shutdown = state.shutdown.clone();
+    ws.on_upgrade(move |socket| forward_to_ws(socket, addr, channel, rx, metrics, shutdown))
+}
+
+#[tracing::instrument(name = "client", skip_all, fields(transport = "ws", %addr, %channel))]
+async fn forward_to_ws(
+    mut socket: WebSocket,
+    addr: SocketAddr,
+    channel: String,
+    mut rx: broadcast::Receiver<BroadcastMsg>,
+    metrics: Arc<Metrics>,
+    shutdown: Shutdown,
+) {
+    info!("websocket client subscribed");
+    let client = addr.to_string();
+    metrics.client_connected("ws");
+
+    loop {
+        let received = tokio::select! {
+            received = rx.recv() => received,
+            _ = shutdown.wait() => {
+                let frame = CloseFrame { code: close_code::AWAY, reason: "server shutting down".into() };
+                let _ = socket.send(Message::Close(Some(frame))).await;
+                break;
+            }
+        };
+        match received {
+            Ok(msg) => {
+                let text = String::from_utf8_lossy(msg.trim_ascii_end()).into_owned();
+                let len = text.len();
+                if socket.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+                metrics.client_sent("ws", &client, len);
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!(skipped, "client lagged");
+                metrics.client_lagged("ws", &client, skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+
+    metrics.client_disconnected("ws", &client);
+    info!("websocket client left");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+    use crate::analytics::bars::BarConfig;
+    use crate::analytics::microstructure::AnalyticsConfig;
+    use crate::snapshot::SnapshotConfig;
+    use crate::types::msg::{MboMsg, RecordHeader, ACTION_ADD, ACTION_CANCEL, FLAG_LAST, SIDE_ASK, SIDE_BID};
+    use crate::types::pretty::MessageFormat;
+
+    fn app_state(order_history: Option<usize>) -> AppState {
+        AppState::new(&ServerConfig {
+            addr: String::new(),
+            http_addr: String::new(),
+            source: "CLX5_mbo.dbn".parse().unwrap(),
+            sleep_time: 0,
+            snapshots: SnapshotConfig::default(),
+            recorder: None,
+            analytics: AnalyticsConfig::default(),
+            bars: BarConfig::default(),
+            order_history,
+            message_cache: 100,
+            tcp_format: MessageFormat::default(),
+            delta_depth: 10,
+            bbo_addr: None,
+            bbo_file: None,
+            checkpoint: None,
+            resume_from: None,
+        })
+    }
+
+    fn msg(sequence: u32, action: std::ffi::c_char, side: std::ffi::c_char) -> MboMsg {
+        MboMsg {
+            hd: RecordHeader { rtype: 0xA0, publisher_id: 1, instrument_id: 1, ts_event: u64::from(sequence) },
+            order_id: u64::from(sequence),
+            price: 1_000_000_000,
+            size: 1,
+            flags: FLAG_LAST,
+            channel_id: 0,
+            action,
+            side,
+            ts_recv: 0,
+            ts_in_delta: 0,
+            sequence,
+        }
+    }
+
+    async fn get(state: AppState, uri: &str) -> (StatusCode, String) {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = router(state).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn messages_url_from_the_readme_treats_empty_values_as_unset() {
+        let state = app_state(None);
+        {
+            let mut cache = state.cache.lock().unwrap();
+            cache.push(msg(1, ACTION_ADD, SIDE_BID));
+            cache.push(msg(2, ACTION_ADD, SIDE_ASK));
+            cache.push(msg(3, ACTION_CANCEL, SIDE_BID));
+            cache.push(msg(4, ACTION_ADD, SIDE_BID));
+        }
+
+        let uri = "/api/messages?instrument=&side=B&action=A&since_sequence=&limit=100&cursor=";
+        let (status, body) = get(state.clone(), uri).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let sequences: Vec<u64> =
+            page["messages"].as_array().unwrap().iter().map(|m| m["sequence"].as_u64().unwrap()).collect();
+        assert_eq!(sequences, [1, 4]);
+        assert_eq!(page["next_cursor"], 3);
+
+        let (status, _) = get(state, "/api/messages?instrument=x").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn metrics_render_and_errors_share_one_response_shape() {
+        let state = app_state(None);
+        state.metrics.messages_decoded.inc();
+        let (status, body) = get(state, "/metrics").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.lines().any(|l| l.starts_with("hft_messages_decoded_total 1")), "{}", body);
+
+        let response = Error::Metrics(prometheus::Error::Msg("registry poisoned".to_string())).into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.starts_with("metrics error: ") && body.ends_with("registry poisoned"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn order_lookups_tell_a_disabled_store_from_an_unknown_order() {
+        let (status, body) = get(app_state(None), "/api/orders/1").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body, "order history disabled; start with --order-history");
+
+        let state = app_state(Some(10));
+        state.orders.as_ref().unwrap().lock().unwrap().record(&msg(1, ACTION_ADD, SIDE_BID));
+        let (status, body) = get(state.clone(), "/api/orders/2").await;
+        assert_eq!((status, body.as_str()), (StatusCode::NOT_FOUND, "order 2 not found"));
+
+        let (status, body) = get(state, "/api/orders/1").await;
+        assert_eq!(status, StatusCode::OK);
+        let orders: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!((orders[0]["order_id"].as_u64(), orders[0]["status"].as_str()), (Some(1), Some("open")));
+    }
+}
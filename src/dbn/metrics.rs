@@ -0,0 +1,140 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+/// Latency buckets in seconds, 1us .. 100ms.
+const LATENCY_BUCKETS: [f64; 11] = [
+    1e-6, 2.5e-6, 5e-6, 1e-5, 2.5e-5, 5e-5, 1e-4, 1e-3, 1e-2, 5e-2, 1e-1,
+];
+
+/// Prometheus metrics exported on `/metrics`.
+/// Per-client series are labelled by peer address and dropped when the client leaves.
+pub struct Metrics {
+    registry: Registry,
+    pub messages_decoded: IntCounter,
+    /// MBO messages sent to at least one subscriber.
+    pub messages_broadcast: IntCounter,
+    /// Messages the book refused, e.g. unknown action or side codes.
+    pub messages_rejected: IntCounter,
+    pub client_bytes_sent: IntCounterVec,
+    pub client_lag_events: IntCounterVec,
+    pub client_lagged_messages: IntCounterVec,
+    pub connected_clients: IntGaugeVec,
+    pub book_apply_seconds: Histogram,
+    pub snapshot_write_seconds: Histogram,
+    /// Messages read from the source so far.
+    pub replay_index: IntGauge,
+    pub replay_ts_event: IntGauge,
+    /// Messages read during the last second, set by the rate monitor.
+    pub messages_per_second: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let client_labels = &["transport", "client"];
+        let latency = |name: &str, help: &str| {
+            Histogram::with_opts(HistogramOpts::new(name, help).buckets(LATENCY_BUCKETS.to_vec()))
+                .expect("valid histogram opts")
+        };
+
+        let metrics = Self {
+            registry: Registry::new(),
+            messages_decoded: IntCounter::new("hft_messages_decoded_total", "MBO messages decoded from the source")
+                .expect("valid counter opts"),
+            messages_broadcast: IntCounter::new(
+                "hft_messages_broadcast_total",
+                "MBO messages broadcast to at least one client",
+            )
+            .expect("valid counter opts"),
+            messages_rejected: IntCounter::new(
+                "hft_messages_rejected_total",
+                "MBO messages rejected by the order book",
+            )
+            .expect("valid counter opts"),
+            client_bytes_sent: IntCounterVec::new(
+                Opts::new("hft_client_bytes_sent_total", "Bytes written to each connected client"),
+                client_labels,
+            )
+            .expect("valid counter opts"),
+            client_lag_events: IntCounterVec::new(
+                Opts::new("hft_client_lag_events_total", "Times a client fell behind the broadcast channel"),
+                client_labels,
+            )
+            .expect("valid counter opts"),
+            client_lagged_messages: IntCounterVec::new(
+                Opts::new("hft_client_lagged_messages_total", "Messages skipped by lagging clients"),
+                client_labels,
+            )
+            .expect("valid counter opts"),
+            connected_clients: IntGaugeVec::new(
+                Opts::new("hft_connected_clients", "Currently connected clients"),
+                &["transport"],
+            )
+            .expect("valid gauge opts"),
+            book_apply_seconds: latency("hft_book_apply_seconds", "Time to apply one MBO message to the market"),
+            snapshot_write_seconds: latency("hft_snapshot_write_seconds", "Time to write one book snapshot"),
+            replay_index: IntGauge::new("hft_replay_index", "Messages read from the source").expect("valid gauge opts"),
+            replay_ts_event: IntGauge::new("hft_replay_ts_event", "ts_event of the last message read, in ns")
+                .expect("valid gauge opts"),
+            messages_per_second: IntGauge::new("hft_messages_per_second", "Messages read during the last second")
+                .expect("valid gauge opts"),
+        };
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 12] = [
+            Box::new(metrics.messages_decoded.clone()),
+            Box::new(metrics.messages_broadcast.clone()),
+            Box::new(metrics.messages_rejected.clone()),
+            Box::new(metrics.client_bytes_sent.clone()),
+            Box::new(metrics.client_lag_events.clone()),
+            Box::new(metrics.client_lagged_messages.clone()),
+            Box::new(metrics.connected_clients.clone()),
+            Box::new(metrics.book_apply_seconds.clone()),
+            Box::new(metrics.snapshot_write_seconds.clone()),
+            Box::new(metrics.replay_index.clone()),
+            Box::new(metrics.replay_ts_event.clone()),
+            Box::new(metrics.messages_per_second.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector).expect("metric registered once");
+        }
+        metrics
+    }
+
+    pub fn client_connected(&self, transport: &str) {
+        self.connected_clients.with_label_values(&[transport]).inc();
+    }
+
+    pub fn client_disconnected(&self, transport: &str, client: &str) {
+        self.connected_clients.with_label_values(&[transport]).dec();
+        for vec in [&self.client_bytes_sent, &self.client_lag_events, &self.client_lagged_messages] {
+            let _ = vec.remove_label_values(&[transport, client]);
+        }
+    }
+
+    pub fn client_sent(&self, transport: &str, client: &str, bytes: usize) {
+        self.client_bytes_sent.with_label_values(&[transport, client]).inc_by(bytes as u64);
+    }
+
+    pub fn client_lagged(&self, transport: &str, client: &str, skipped: u64) {
+        self.client_lag_events.with_label_values(&[transport, client]).inc();
+        self.client_lagged_messages.with_label_values(&[transport, client]).inc_by(skipped);
+    }
+
+    pub fn connected(&self, transport: &str) -> u64 {
+        self.connected_clients.with_label_values(&[transport]).get().max(0) as u64
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> crate::Result<String> {
+        let mut buf = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buf)?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1 +1,13 @@
+pub mod batch;
+pub mod cache;
+pub mod checkpoint;
 pub mod dbn_local;
+pub mod feeds;
+pub mod gateway;
+pub mod http;
+pub mod metrics;
+pub mod recorder;
+pub mod shutdown;
+pub mod source;
+pub mod status;
+pub mod synth;
@@ -0,0 +1,139 @@
+use dbn::encode::dbn::{Encoder, MetadataEncoder};
+use dbn::encode::EncodeRecord;
+use dbn::Metadata;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::num::NonZeroU64;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use crate::dbn::source::SourceError;
+use tracing::info;
+use crate::types::msg::MboMsg as C_MboMsg;
+
+/// Where and how often the outbound stream is written to DBN files.
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    pub dir: PathBuf,
+    pub prefix: String,
+    /// Start a new file once the current one reaches this many bytes.
+    pub max_bytes: Option<u64>,
+    /// Start a new file once the current one has been open this long.
+    pub max_age: Option<Duration>,
+}
+
+impl RecorderConfig {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            prefix: "outbound".to_string(),
+            max_bytes: None,
+            max_age: None,
+        }
+    }
+}
+
+struct OpenFile {
+    path: PathBuf,
+    encoder: Encoder<BufWriter<File>>,
+    opened_at: Instant,
+    /// Record bytes written, excluding the metadata header.
+    bytes: u64,
+    records: u64,
+    first_ts: u64,
+    last_ts: u64,
+}
+
+/// Writes every MBO record the book accepted to rotating DBN files. Each file carries
+/// the source metadata with `start`/`end` narrowed to the records it holds.
+pub struct DbnRecorder {
+    cfg: RecorderConfig,
+    metadata: Metadata,
+    current: Option<OpenFile>,
+    file_index: u32,
+}
+
+const RECORD_SIZE: u64 = std::mem::size_of::<dbn::record::MboMsg>() as u64;
+
+impl DbnRecorder {
+    pub fn new(cfg: RecorderConfig, metadata: Metadata) -> Result<Self, SourceError> {
+        fs::create_dir_all(&cfg.dir)?;
+        Ok(Self { cfg, metadata, current: None, file_index: 0 })
+    }
+
+    pub fn record(&mut self, msg: &C_MboMsg) -> Result<(), SourceError> {
+        if self.should_rotate() {
+            self.close_current()?;
+        }
+        if self.current.is_none() {
+            self.open_next(msg.ts_event())?;
+        }
+
+        let file = self.current.as_mut().expect("recorder file opened above");
+        file.encoder.encode_record(&dbn::record::MboMsg::from(msg))?;
+        file.bytes += RECORD_SIZE;
+        file.records += 1;
+        file.last_ts = msg.ts_event();
+        Ok(())
+    }
+
+    /// Flushes and finalizes the current file.
+    pub fn finish(&mut self) -> Result<(), SourceError> {
+        self.close_current()
+    }
+
+    fn should_rotate(&self) -> bool {
+        let Some(file) = &self.current else {
+            return false;
+        };
+        let too_big = self.cfg.max_bytes.is_some_and(|max| file.bytes >= max);
+        let too_old = self.cfg.max_age.is_some_and(|max| file.opened_at.elapsed() >= max);
+        too_big || too_old
+    }
+
+    fn open_next(&mut self, first_ts: u64) -> Result<(), SourceError> {
+        let path = self
+            .cfg
+            .dir
+            .join(format!("{}_{:05}.dbn", self.cfg.prefix, self.file_index));
+        self.file_index += 1;
+
+        let mut metadata = self.metadata.clone();
+        metadata.start = first_ts;
+        metadata.end = None;
+        metadata.limit = None;
+
+        let encoder = Encoder::new(BufWriter::new(File::create(&path)?), &metadata)?;
+        self.current = Some(OpenFile {
+            path,
+            encoder,
+            opened_at: Instant::now(),
+            bytes: 0,
+            records: 0,
+            first_ts,
+            last_ts: first_ts,
+        });
+        Ok(())
+    }
+
+    fn close_current(&mut self) -> Result<(), SourceError> {
+        let Some(mut file) = self.current.take() else {
+            return Ok(());
+        };
+        file.encoder.flush()?;
+        let writer = file.encoder.get_mut();
+        writer.flush()?;
+
+        // Rewrite the header range now that the last record is known.
+        let end = NonZeroU64::new(file.last_ts.saturating_add(1));
+        MetadataEncoder::new(writer.get_mut()).update_encoded(
+            self.metadata.version,
+            file.first_ts,
+            end,
+            None,
+        )?;
+        writer.get_ref().sync_all()?;
+
+        info!(records = file.records, bytes = file.bytes, path = %file.path.display(), "recording closed");
+        Ok(())
+    }
+}
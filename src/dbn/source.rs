@@ -0,0 +1,297 @@
+use dbn::decode::dbn::{MetadataDecoder, RecordDecoder};
+use dbn::record::{InstrumentDefMsg, MboMsg};
+use dbn::{rtype, Metadata, Record, SType, Schema, VersionUpgradePolicy};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::dbn::synth::SynthSpec;
+use crate::types::msg::MboMsg as C_MboMsg;
+
+pub type SourceError = crate::Error;
+
+/// A blocking producer of MBO records, polled from the reader task.
+pub trait MarketDataSource: Send {
+    /// Returns the next record, or `None` once the source is exhausted.
+    fn next_mbo(&mut self) -> Result<Option<C_MboMsg>, SourceError>;
+
+    /// Short human readable description used in logs.
+    fn describe(&self) -> String;
+
+    /// DBN metadata of the underlying stream, if it has any.
+    fn metadata(&self) -> Option<&Metadata> {
+        None
+    }
+
+    /// Byte offset of the next record, for sources that can be reopened there.
+    fn position(&self) -> Option<u64> {
+        None
+    }
+
+    /// Instrument definitions read since the last call, in stream order. They
+    /// precede the record last returned by `next_mbo`.
+    fn take_definitions(&mut self) -> Vec<InstrumentDefMsg> {
+        Vec::new()
+    }
+
+    /// Handle that unblocks a `next_mbo` waiting on the network, for sources
+    /// whose reads can block indefinitely. That call then fails or returns `None`.
+    fn interrupter(&self) -> Option<SourceInterrupter> {
+        None
+    }
+}
+
+/// Aborts a source's pending and future reads from another thread.
+pub struct SourceInterrupter(TcpStream);
+
+impl SourceInterrupter {
+    pub fn interrupt(&self) {
+        let _ = self.0.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+/// Minimal MBO metadata for streams that don't carry their own.
+pub fn default_mbo_metadata(start: u64) -> Metadata {
+    Metadata::builder()
+        .dataset("UNKNOWN")
+        .schema(Some(Schema::Mbo))
+        .start(start)
+        .stype_in(Some(SType::InstrumentId))
+        .stype_out(SType::InstrumentId)
+        .build()
+}
+
+/// Where the pipeline should pull its records from.
+#[derive(Debug, Clone)]
+pub enum SourceSpec {
+    /// Replay a local DBN file.
+    File(PathBuf),
+    /// Connect to a gateway streaming DBN over TCP (`tcp://host:port`).
+    Tcp(String),
+    /// Generate a seeded synthetic stream (`synth://seed=7,instruments=8`).
+    Synth(SynthSpec),
+}
+
+impl SourceSpec {
+    pub fn open(&self) -> Result<Box<dyn MarketDataSource>, SourceError> {
+        match self {
+            SourceSpec::File(path) => Ok(Box::new(DbnFileSource::open(path.clone())?)),
+            SourceSpec::Tcp(addr) => Ok(Box::new(DbnTcpSource::connect(addr)?)),
+            SourceSpec::Synth(spec) => Ok(Box::new(spec.generator())),
+        }
+    }
+
+    /// Opens the source with its first record at byte `offset`, as reported by
+    /// `MarketDataSource::position`. Only files can be reopened mid-stream.
+    pub fn open_at(&self, offset: u64) -> Result<Box<dyn MarketDataSource>, SourceError> {
+        match self {
+            SourceSpec::File(path) => Ok(Box::new(DbnFileSource::open_at(path.clone(), offset)?)),
+            SourceSpec::Tcp(addr) => Err(SourceError::InvalidInput(format!(
+                "cannot resume gateway tcp://{} at an offset",
+                addr
+            ))),
+            SourceSpec::Synth(_) => Err(SourceError::InvalidInput(
+                "cannot resume a synthetic stream at an offset".to_string(),
+            )),
+        }
+    }
+}
+
+impl FromStr for SourceSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(addr) = s.strip_prefix("tcp://") {
+            if addr.is_empty() {
+                return Err("missing address after tcp://".to_string());
+            }
+            Ok(SourceSpec::Tcp(addr.to_string()))
+        } else if let Some(spec) = s.strip_prefix("synth://") {
+            spec.parse().map(SourceSpec::Synth)
+        } else if s.is_empty() {
+            Err("empty source".to_string())
+        } else {
+            Ok(SourceSpec::File(PathBuf::from(s.strip_prefix("file://").unwrap_or(s))))
+        }
+    }
+}
+
+impl std::fmt::Display for SourceSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceSpec::File(path) => write!(f, "{}", path.display()),
+            SourceSpec::Tcp(addr) => write!(f, "tcp://{}", addr),
+            SourceSpec::Synth(spec) => write!(f, "{}", spec),
+        }
+    }
+}
+
+// ============ DBN DECODER SOURCE ============
+
+/// Decodes MBO records from any DBN byte stream. Records are read as stored
+/// (no version upgrade) so `position` advances by their on-disk size;
+/// definitions are kept for `take_definitions` and other schemas are skipped.
+struct DbnStreamSource<R: Read> {
+    decoder: RecordDecoder<R>,
+    metadata: Metadata,
+    /// Offset of the next record in the stream.
+    position: u64,
+    definitions: Vec<InstrumentDefMsg>,
+}
+
+impl<R: Read> DbnStreamSource<R> {
+    /// `header_len` is the size of the DBN prelude and metadata, if known.
+    fn new(reader: R, header_len: u64) -> Result<Self, SourceError> {
+        let mut metadata_decoder = MetadataDecoder::new(reader);
+        let metadata = metadata_decoder.decode()?;
+        let mut decoder = RecordDecoder::from(metadata_decoder);
+        decoder.set_upgrade_policy(VersionUpgradePolicy::AsIs)?;
+        Ok(Self::with_decoder(decoder, metadata, header_len))
+    }
+
+    fn with_decoder(decoder: RecordDecoder<R>, metadata: Metadata, position: u64) -> Self {
+        Self { decoder, metadata, position, definitions: Vec::new() }
+    }
+
+    fn next_mbo(&mut self) -> Result<Option<C_MboMsg>, SourceError> {
+        while let Some(record) = self.decoder.decode_ref()? {
+            self.position += record.record_size() as u64;
+            if let Some(msg) = record.get::<MboMsg>() {
+                return Ok(Some(C_MboMsg::from(msg)));
+            }
+            if record.header().rtype == rtype::INSTRUMENT_DEF {
+                let def = match self.metadata.version {
+                    1 => record.get::<dbn::v1::InstrumentDefMsg>().map(InstrumentDefMsg::from),
+                    2 => record.get::<dbn::v2::InstrumentDefMsg>().map(InstrumentDefMsg::from),
+                    _ => record.get::<InstrumentDefMsg>().cloned(),
+                };
+                self.definitions.extend(def);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Reads the DBN prelude (`DBN`, version byte, metadata length) and returns
+/// the version and the offset of the first record.
+fn read_prelude(path: &Path) -> Result<(u8, u64), SourceError> {
+    let mut prelude = [0u8; 8];
+    File::open(path)?.read_exact(&mut prelude)?;
+    if &prelude[..3] != b"DBN" {
+        return Err(SourceError::InvalidInput(format!(
+            "{} is not an uncompressed DBN file",
+            path.display()
+        )));
+    }
+    let metadata_len = u32::from_le_bytes([prelude[4], prelude[5], prelude[6], prelude[7]]);
+    Ok((prelude[3], 8 + u64::from(metadata_len)))
+}
+
+// ============ FILE REPLAY ============
+
+/// Replays a DBN file from disk.
+pub struct DbnFileSource {
+    path: PathBuf,
+    inner: DbnStreamSource<BufReader<File>>,
+}
+
+impl DbnFileSource {
+    pub fn open(path: PathBuf) -> Result<Self, SourceError> {
+        let (_, header_len) = read_prelude(&path)?;
+        let file = File::open(&path)?;
+        let inner = DbnStreamSource::new(BufReader::new(file), header_len)?;
+        Ok(Self { path, inner })
+    }
+
+    /// Opens the file with the next record at byte `offset`, which must be a
+    /// record boundary (e.g. a previous `position()`).
+    pub fn open_at(path: PathBuf, offset: u64) -> Result<Self, SourceError> {
+        let (version, header_len) = read_prelude(&path)?;
+        let file_len = std::fs::metadata(&path)?.len();
+        if offset < header_len || offset > file_len {
+            return Err(SourceError::InvalidInput(format!(
+                "offset {} is outside the records of {} ({}..{})",
+                offset,
+                path.display(),
+                header_len,
+                file_len
+            )));
+        }
+        let metadata = MetadataDecoder::new(BufReader::new(File::open(&path)?)).decode()?;
+        let mut file = File::open(&path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let decoder = RecordDecoder::with_version(
+            BufReader::new(file),
+            version,
+            VersionUpgradePolicy::AsIs,
+            metadata.ts_out,
+        )?;
+        let inner = DbnStreamSource::with_decoder(decoder, metadata, offset);
+        Ok(Self { path, inner })
+    }
+}
+
+impl MarketDataSource for DbnFileSource {
+    fn next_mbo(&mut self) -> Result<Option<C_MboMsg>, SourceError> {
+        self.inner.next_mbo()
+    }
+
+    fn describe(&self) -> String {
+        format!("file {}", self.path.display())
+    }
+
+    fn metadata(&self) -> Option<&Metadata> {
+        Some(&self.inner.metadata)
+    }
+
+    fn position(&self) -> Option<u64> {
+        Some(self.inner.position)
+    }
+
+    fn take_definitions(&mut self) -> Vec<InstrumentDefMsg> {
+        std::mem::take(&mut self.inner.definitions)
+    }
+}
+
+// ============ TCP GATEWAY ============
+
+/// Reads a DBN stream from a live gateway (or the local mock in `gateway`).
+pub struct DbnTcpSource {
+    addr: String,
+    inner: DbnStreamSource<BufReader<TcpStream>>,
+    /// Clone of the socket for `interrupter`.
+    control: TcpStream,
+}
+
+impl DbnTcpSource {
+    pub fn connect(addr: &str) -> Result<Self, SourceError> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        let control = stream.try_clone()?;
+        let inner = DbnStreamSource::new(BufReader::new(stream), 0)?;
+        Ok(Self { addr: addr.to_string(), inner, control })
+    }
+}
+
+impl MarketDataSource for DbnTcpSource {
+    fn next_mbo(&mut self) -> Result<Option<C_MboMsg>, SourceError> {
+        self.inner.next_mbo()
+    }
+
+    fn describe(&self) -> String {
+        format!("gateway tcp://{}", self.addr)
+    }
+
+    fn metadata(&self) -> Option<&Metadata> {
+        Some(&self.inner.metadata)
+    }
+
+    fn take_definitions(&mut self) -> Vec<InstrumentDefMsg> {
+        std::mem::take(&mut self.inner.definitions)
+    }
+
+    fn interrupter(&self) -> Option<SourceInterrupter> {
+        self.control.try_clone().ok().map(SourceInterrupter)
+    }
+}
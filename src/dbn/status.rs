@@ -0,0 +1,106 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayState {
+    /// The source has not been opened yet.
+    Starting,
+    Running,
+    Paused,
+    /// The source is exhausted or the replay was stopped by shutdown.
+    Finished,
+    Error,
+}
+
+struct Inner {
+    state: ReplayState,
+    source: String,
+    error: Option<String>,
+}
+
+/// Lifecycle of the replay, shared between the reader and the HTTP handlers.
+/// Position and rate live in `Metrics`; this only tracks what they can't express.
+pub struct ReplayStatus {
+    inner: Mutex<Inner>,
+    paused: AtomicBool,
+    started: Instant,
+}
+
+impl ReplayStatus {
+    pub fn new(source: String) -> Self {
+        Self {
+            inner: Mutex::new(Inner { state: ReplayState::Starting, source, error: None }),
+            paused: AtomicBool::new(false),
+            started: Instant::now(),
+        }
+    }
+
+    /// Reports `Paused` while a running replay is paused.
+    pub fn state(&self) -> ReplayState {
+        match self.inner.lock().unwrap().state {
+            ReplayState::Running if self.is_paused() => ReplayState::Paused,
+            state => state,
+        }
+    }
+
+    pub fn source(&self) -> String {
+        self.inner.lock().unwrap().source.clone()
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.inner.lock().unwrap().error.clone()
+    }
+
+    /// Called once the source is open; `source` is its description.
+    pub fn running(&self, source: String) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = ReplayState::Running;
+        inner.source = source;
+    }
+
+    pub fn finished(&self) {
+        self.inner.lock().unwrap().state = ReplayState::Finished;
+    }
+
+    pub fn failed(&self, error: String) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = ReplayState::Error;
+        inner.error = Some(error);
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Ready once the source is open and nothing has failed.
+    pub fn is_ready(&self) -> bool {
+        matches!(self.state(), ReplayState::Running | ReplayState::Paused | ReplayState::Finished)
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+/// Body of `/api/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub state: ReplayState,
+    pub source: String,
+    pub error: Option<String>,
+    /// Messages read from the source so far.
+    pub index: u64,
+    /// `ts_event` of the last message read.
+    pub ts_event: Option<u64>,
+    pub msg_per_sec: u64,
+    pub tcp_clients: u64,
+    pub ws_clients: u64,
+    pub uptime_secs: f64,
+}
@@ -0,0 +1,471 @@
+use dbn::encode::dbn::Encoder;
+use dbn::encode::EncodeRecord;
+use dbn::record::InstrumentDefMsg;
+use dbn::Metadata;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::str::FromStr;
+use crate::dbn::source::{default_mbo_metadata, MarketDataSource, SourceError};
+use crate::types::price::Price;
+use crate::types::msg::{
+    RecordHeader, MboMsg as C_MboMsg,
+    ACTION_ADD, ACTION_CANCEL, ACTION_MODIFY, ACTION_TRADE, ACTION_FILL,
+    SIDE_ASK, SIDE_BID, FLAG_LAST,
+};
+use std::os::raw::c_char;
+
+const RTYPE_MBO: u8 = 0xA0;
+
+/// Knobs for the synthetic order flow generator.
+#[derive(Debug, Clone)]
+pub struct SynthConfig {
+    pub seed: u64,
+    pub instruments: u32,
+    pub first_instrument_id: u32,
+    pub publisher_id: u16,
+    /// Mean events per second, per instrument.
+    pub arrival_rate: f64,
+    /// Probability that a non-trade event cancels a resting order.
+    pub cancel_ratio: f64,
+    /// Probability that a non-trade, non-cancel event modifies a resting order.
+    pub modify_ratio: f64,
+    /// Probability that an event is a trade.
+    pub trade_frequency: f64,
+    /// Standard deviation of the mid price, in ticks per sqrt(second).
+    pub volatility: f64,
+    /// Tick size in fixed-point units (1e-9).
+    pub tick_size: i64,
+    /// Starting mid price of the first instrument in fixed-point units.
+    pub start_price: i64,
+    pub start_ts: u64,
+    /// Resting orders kept per instrument before cancels are forced.
+    pub max_orders: usize,
+    pub max_size: u32,
+}
+
+impl Default for SynthConfig {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            instruments: 1,
+            first_instrument_id: 1,
+            publisher_id: 1,
+            arrival_rate: 1_000.0,
+            cancel_ratio: 0.4,
+            modify_ratio: 0.1,
+            trade_frequency: 0.05,
+            volatility: 2.0,
+            tick_size: 10_000_000,
+            start_price: 65_000_000_000,
+            start_ts: 1_700_000_000_000_000_000,
+            max_orders: 500,
+            max_size: 20,
+        }
+    }
+}
+
+/// A generator as a pipeline source: `synth://seed=7,instruments=8,messages=1000000`.
+/// Keys are the `generate` flags (`arrival-rate`, `cancel-ratio`, `modify-ratio`,
+/// `trade-frequency`, `volatility`); omitted ones keep their defaults, and
+/// without `messages` the stream never ends.
+#[derive(Debug, Clone, Default)]
+pub struct SynthSpec {
+    pub cfg: SynthConfig,
+    pub messages: Option<usize>,
+}
+
+impl SynthSpec {
+    pub fn generator(&self) -> SynthGenerator {
+        let generator = SynthGenerator::new(self.cfg.clone());
+        match self.messages {
+            Some(count) => generator.with_limit(count),
+            None => generator,
+        }
+    }
+}
+
+impl FromStr for SynthSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn num<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+            value.parse().map_err(|_| format!("invalid synth {} '{}'", key, value))
+        }
+        let mut spec = SynthSpec::default();
+        for pair in s.split(',').filter(|p| !p.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value in synth spec, got '{}'", pair))?;
+            match key {
+                "seed" => spec.cfg.seed = num(key, value)?,
+                "instruments" => spec.cfg.instruments = num(key, value)?,
+                "messages" => spec.messages = Some(num(key, value)?),
+                "arrival-rate" => spec.cfg.arrival_rate = num(key, value)?,
+                "cancel-ratio" => spec.cfg.cancel_ratio = num(key, value)?,
+                "modify-ratio" => spec.cfg.modify_ratio = num(key, value)?,
+                "trade-frequency" => spec.cfg.trade_frequency = num(key, value)?,
+                "volatility" => spec.cfg.volatility = num(key, value)?,
+                _ => return Err(format!("unknown synth key '{}'", key)),
+            }
+        }
+        Ok(spec)
+    }
+}
+
+impl std::fmt::Display for SynthSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cfg = &self.cfg;
+        write!(
+            f,
+            "synth://seed={},instruments={},arrival-rate={},cancel-ratio={},modify-ratio={},trade-frequency={},volatility={}",
+            cfg.seed, cfg.instruments, cfg.arrival_rate, cfg.cancel_ratio, cfg.modify_ratio, cfg.trade_frequency, cfg.volatility
+        )?;
+        if let Some(messages) = self.messages {
+            write!(f, ",messages={}", messages)?;
+        }
+        Ok(())
+    }
+}
+
+struct SynthOrder {
+    order_id: u64,
+    side: c_char,
+    price: i64,
+    size: u32,
+}
+
+struct SynthBook {
+    instrument_id: u32,
+    mid_ticks: f64,
+    orders: Vec<SynthOrder>,
+}
+
+impl SynthBook {
+    fn best(&self, side: c_char) -> Option<usize> {
+        let candidates = self.orders.iter().enumerate().filter(|(_, o)| o.side == side);
+        if side == SIDE_BID {
+            candidates.max_by_key(|(i, o)| (o.price, std::cmp::Reverse(*i))).map(|(i, _)| i)
+        } else {
+            candidates.min_by_key(|(i, o)| (o.price, *i)).map(|(i, _)| i)
+        }
+    }
+
+    fn best_price(&self, side: c_char) -> Option<i64> {
+        self.best(side).map(|i| self.orders[i].price)
+    }
+}
+
+/// Seeded generator of a consistent MBO stream: adds, modifies, cancels and
+/// trades (T, F, C sequences) over a random-walk mid price per instrument.
+pub struct SynthGenerator {
+    cfg: SynthConfig,
+    rng: StdRng,
+    books: Vec<SynthBook>,
+    ts: u64,
+    next_order_id: u64,
+    sequence: u32,
+    pending: VecDeque<C_MboMsg>,
+    remaining: Option<usize>,
+    metadata: Metadata,
+    /// One definition per instrument, carrying the tick size; handed out once.
+    definitions: Vec<InstrumentDefMsg>,
+}
+
+impl SynthGenerator {
+    pub fn new(cfg: SynthConfig) -> Self {
+        let tick = cfg.tick_size.max(1) as f64;
+        let books: Vec<SynthBook> = (0..cfg.instruments.max(1))
+            .map(|i| SynthBook {
+                instrument_id: cfg.first_instrument_id + i,
+                mid_ticks: (cfg.start_price as f64 / tick).round() + f64::from(i) * 100.0,
+                orders: Vec::new(),
+            })
+            .collect();
+        let mut metadata = default_mbo_metadata(cfg.start_ts);
+        metadata.dataset = "SYNTH".to_string();
+        let definitions = books
+            .iter()
+            .map(|book| InstrumentDefMsg {
+                hd: dbn::RecordHeader::new::<InstrumentDefMsg>(
+                    dbn::rtype::INSTRUMENT_DEF,
+                    cfg.publisher_id,
+                    book.instrument_id,
+                    cfg.start_ts,
+                ),
+                ts_recv: cfg.start_ts,
+                min_price_increment: cfg.tick_size.max(1),
+                ..InstrumentDefMsg::default()
+            })
+            .collect();
+        Self {
+            rng: StdRng::seed_from_u64(cfg.seed),
+            ts: cfg.start_ts,
+            cfg,
+            books,
+            next_order_id: 1,
+            sequence: 0,
+            pending: VecDeque::new(),
+            remaining: None,
+            metadata,
+            definitions,
+        }
+    }
+
+    /// Stops the stream after `count` messages.
+    pub fn with_limit(mut self, count: usize) -> Self {
+        self.remaining = Some(count);
+        self
+    }
+
+    /// Writes the instrument definitions and the (limited) stream to a DBN file,
+    /// returning the record count.
+    pub fn write_dbn(mut self, path: impl AsRef<Path>) -> Result<u64, SourceError> {
+        if self.remaining.is_none() {
+            return Err(SourceError::InvalidInput("refusing to write an unbounded synthetic stream".to_string()));
+        }
+        let metadata = self.metadata.clone();
+        let mut encoder = Encoder::new(BufWriter::new(File::create(path)?), &metadata)?;
+        let mut written = 0u64;
+        for def in self.take_definitions() {
+            encoder.encode_record(&def)?;
+            written += 1;
+        }
+        for msg in &mut self {
+            encoder.encode_record(&dbn::record::MboMsg::from(&msg))?;
+            written += 1;
+        }
+        encoder.flush()?;
+        Ok(written)
+    }
+
+    fn advance_clock(&mut self) {
+        let rate = self.cfg.arrival_rate.max(f64::MIN_POSITIVE) * self.books.len() as f64;
+        let u: f64 = self.rng.gen_range(f64::EPSILON..1.0);
+        let dt_secs = -u.ln() / rate;
+        self.ts += ((dt_secs * 1e9) as u64).max(1);
+
+        // Brownian step of the mid over the elapsed interval.
+        let sigma = self.cfg.volatility * dt_secs.sqrt();
+        for i in 0..self.books.len() {
+            let z = self.standard_normal();
+            self.books[i].mid_ticks += sigma * z;
+        }
+    }
+
+    fn standard_normal(&mut self) -> f64 {
+        let u1: f64 = self.rng.gen_range(f64::EPSILON..1.0);
+        let u2: f64 = self.rng.gen();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+
+    fn next_event(&mut self) {
+        self.advance_clock();
+        let book_idx = self.rng.gen_range(0..self.books.len());
+        let live = self.books[book_idx].orders.len();
+
+        let trade = live > 0 && self.rng.gen_bool(self.cfg.trade_frequency.clamp(0.0, 1.0));
+        if trade && self.emit_trade(book_idx) {
+            return;
+        }
+
+        if live >= self.cfg.max_orders.max(1)
+            || (live > 0 && self.rng.gen_bool(self.cfg.cancel_ratio.clamp(0.0, 1.0)))
+        {
+            self.emit_cancel(book_idx);
+        } else if live > 0 && self.rng.gen_bool(self.cfg.modify_ratio.clamp(0.0, 1.0)) {
+            self.emit_modify(book_idx);
+        } else {
+            self.emit_add(book_idx);
+        }
+    }
+
+    fn tick(&self) -> Price {
+        Price::new(self.cfg.tick_size.max(1)).unwrap_or(Price::ZERO)
+    }
+
+    /// Picks a passive price `ticks_away` from the mid that never crosses the opposite side.
+    fn passive_price(&self, book_idx: usize, side: c_char, ticks_away: i64) -> i64 {
+        let book = &self.books[book_idx];
+        let tick = self.tick();
+        let mid = Price::new((book.mid_ticks * tick.raw() as f64) as i64)
+            .and_then(|mid| mid.round_to_tick(tick))
+            .unwrap_or(tick);
+        let ticks = if side == SIDE_BID { -ticks_away } else { ticks_away };
+        let px = mid.checked_add_ticks(ticks, tick).unwrap_or(mid).raw();
+        let tick = tick.raw();
+        if side == SIDE_BID {
+            book.best_price(SIDE_ASK).map_or(px, |ask| px.min(ask - tick))
+        } else {
+            book.best_price(SIDE_BID).map_or(px, |bid| px.max(bid + tick))
+        }
+    }
+
+    fn emit_add(&mut self, book_idx: usize) {
+        let side = if self.rng.gen_bool(0.5) { SIDE_BID } else { SIDE_ASK };
+        let ticks_away = 1 + self.rng.gen_range(0..10);
+        let price = self.passive_price(book_idx, side, ticks_away);
+        let size = self.rng.gen_range(1..=self.cfg.max_size.max(1));
+        let order_id = self.next_order_id;
+        self.next_order_id += 1;
+
+        self.books[book_idx].orders.push(SynthOrder { order_id, side, price, size });
+        self.push(book_idx, ACTION_ADD, side, order_id, price, size, true);
+    }
+
+    fn emit_cancel(&mut self, book_idx: usize) {
+        let idx = self.rng.gen_range(0..self.books[book_idx].orders.len());
+        let order = self.books[book_idx].orders.swap_remove(idx);
+        self.push(book_idx, ACTION_CANCEL, order.side, order.order_id, order.price, order.size, true);
+    }
+
+    fn emit_modify(&mut self, book_idx: usize) {
+        let idx = self.rng.gen_range(0..self.books[book_idx].orders.len());
+        let side = self.books[book_idx].orders[idx].side;
+        let ticks_away = 1 + self.rng.gen_range(0..10);
+        let price = self.passive_price(book_idx, side, ticks_away);
+        let size = self.rng.gen_range(1..=self.cfg.max_size.max(1));
+
+        let order = &mut self.books[book_idx].orders[idx];
+        order.price = price;
+        order.size = size;
+        let order_id = order.order_id;
+        self.push(book_idx, ACTION_MODIFY, side, order_id, price, size, true);
+    }
+
+    /// Aggresses the best resting order on one side. Returns false if that side is empty.
+    fn emit_trade(&mut self, book_idx: usize) -> bool {
+        let resting_side = if self.rng.gen_bool(0.5) { SIDE_BID } else { SIDE_ASK };
+        let Some(idx) = self.books[book_idx].best(resting_side) else {
+            return false;
+        };
+        let aggressor = if resting_side == SIDE_BID { SIDE_ASK } else { SIDE_BID };
+        let (order_id, price, resting_size) = {
+            let o = &self.books[book_idx].orders[idx];
+            (o.order_id, o.price, o.size)
+        };
+        let fill = self.rng.gen_range(1..=resting_size);
+
+        self.push(book_idx, ACTION_TRADE, aggressor, 0, price, fill, false);
+        self.push(book_idx, ACTION_FILL, resting_side, order_id, price, fill, false);
+        self.push(book_idx, ACTION_CANCEL, resting_side, order_id, price, fill, true);
+
+        let orders = &mut self.books[book_idx].orders;
+        if fill == resting_size {
+            orders.remove(idx);
+        } else {
+            orders[idx].size -= fill;
+        }
+        true
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn push(
+        &mut self,
+        book_idx: usize,
+        action: c_char,
+        side: c_char,
+        order_id: u64,
+        price: i64,
+        size: u32,
+        last: bool,
+    ) {
+        let ts_in_delta = self.rng.gen_range(1_000..20_000);
+        self.sequence = self.sequence.wrapping_add(1);
+        self.pending.push_back(C_MboMsg {
+            hd: RecordHeader {
+                rtype: RTYPE_MBO,
+                publisher_id: self.cfg.publisher_id,
+                instrument_id: self.books[book_idx].instrument_id,
+                ts_event: self.ts,
+            },
+            order_id,
+            price,
+            size,
+            flags: if last { FLAG_LAST } else { 0 },
+            channel_id: 0,
+            action,
+            side,
+            ts_recv: self.ts + ts_in_delta as u64,
+            ts_in_delta,
+            sequence: self.sequence,
+        });
+    }
+}
+
+impl Iterator for SynthGenerator {
+    type Item = C_MboMsg;
+
+    fn next(&mut self) -> Option<C_MboMsg> {
+        if self.remaining == Some(0) {
+            return None;
+        }
+        if self.pending.is_empty() {
+            self.next_event();
+        }
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining -= 1;
+        }
+        self.pending.pop_front()
+    }
+}
+
+impl MarketDataSource for SynthGenerator {
+    fn next_mbo(&mut self) -> Result<Option<C_MboMsg>, SourceError> {
+        Ok(self.next())
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "synthetic generator (seed {}, {} instrument(s))",
+            self.cfg.seed,
+            self.books.len()
+        )
+    }
+
+    fn metadata(&self) -> Option<&Metadata> {
+        Some(&self.metadata)
+    }
+
+    fn take_definitions(&mut self) -> Vec<InstrumentDefMsg> {
+        std::mem::take(&mut self.definitions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(spec: &str, name: &str) -> Vec<u8> {
+        let spec: SynthSpec = spec.parse().unwrap();
+        let path = std::env::temp_dir().join(format!("hft-task-synth-{}-{}.dbn", std::process::id(), name));
+        spec.generator().write_dbn(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn same_seed_writes_identical_bytes() {
+        let a = write("seed=7,instruments=3,messages=20000", "a");
+        let b = write("seed=7,instruments=3,messages=20000", "b");
+        assert!(a.len() > 20_000 * std::mem::size_of::<dbn::record::MboMsg>());
+        assert!(a == b, "same seed produced different files");
+        assert!(a != write("seed=8,instruments=3,messages=20000", "c"));
+    }
+
+    #[test]
+    fn spec_round_trips_through_display() {
+        let spec: SynthSpec = "seed=7,instruments=8,messages=100,trade-frequency=0.2".parse().unwrap();
+        assert_eq!(spec.cfg.seed, 7);
+        assert_eq!(spec.cfg.instruments, 8);
+        assert_eq!(spec.messages, Some(100));
+        let again: SynthSpec = spec.to_string().strip_prefix("synth://").unwrap().parse().unwrap();
+        assert_eq!(again.to_string(), spec.to_string());
+        assert!("seed=x".parse::<SynthSpec>().is_err());
+        assert!("bogus=1".parse::<SynthSpec>().is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use std::io;
+
+/// Crate-wide error type.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Malformed or unreadable DBN data (also covers DBN encoding).
+    #[error("DBN error: {0}")]
+    Decode(#[from] dbn::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    /// A listener could not be bound, e.g. the port is already in use.
+    #[error("failed to bind {addr}: {source}")]
+    Bind {
+        addr: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[error("Arrow error: {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+    /// The Prometheus registry could not be gathered or encoded.
+    #[error("metrics error: {0}")]
+    Metrics(#[from] prometheus::Error),
+    /// A lookup by id (instrument, publisher, order, ...) found nothing.
+    #[error("{0} not found")]
+    NotFound(String),
+    /// A lookup against a feature that was not enabled on the command line.
+    #[error("{feature} disabled; start with {flag}")]
+    Disabled {
+        feature: &'static str,
+        flag: &'static str,
+    },
+    /// A raw `action` or `side` byte that is not a known code.
+    #[error("unknown {field} code {code} ({:?})", char::from(*code as u8))]
+    UnknownCode {
+        field: &'static str,
+        code: std::os::raw::c_char,
+    },
+    /// A well-formed record that cannot be applied, e.g. an add without a side.
+    #[error("invalid record: {0}")]
+    InvalidRecord(String),
+    /// A caller-supplied argument or configuration value is unusable.
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    /// A background task panicked or was cancelled.
+    #[error("task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+impl Error {
+    pub fn bind(addr: impl Into<String>, source: io::Error) -> Self {
+        Error::Bind { addr: addr.into(), source }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
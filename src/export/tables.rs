@@ -0,0 +1,307 @@
+use arrow_array::builder::{
+    ArrayBuilder, Float64Builder, Int64Builder, StringBuilder, UInt16Builder, UInt32Builder,
+    UInt64Builder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+use crate::snapshot::parquet::open_writer;
+use crate::snapshot::SinkError;
+use crate::types::msg::{MboMsg, PriceLevel};
+use crate::types::price::Price;
+
+const BATCH_ROWS: usize = 8192;
+
+/// Columns every exported table starts with.
+struct EventColumns {
+    ts_event: UInt64Builder,
+    ts_recv: UInt64Builder,
+    sequence: UInt32Builder,
+    instrument_id: UInt32Builder,
+    publisher_id: UInt16Builder,
+}
+
+impl EventColumns {
+    fn new() -> Self {
+        Self {
+            ts_event: UInt64Builder::new(),
+            ts_recv: UInt64Builder::new(),
+            sequence: UInt32Builder::new(),
+            instrument_id: UInt32Builder::new(),
+            publisher_id: UInt16Builder::new(),
+        }
+    }
+
+    fn fields() -> Vec<Field> {
+        vec![
+            Field::new("ts_event", DataType::UInt64, false),
+            Field::new("ts_recv", DataType::UInt64, false),
+            Field::new("sequence", DataType::UInt32, false),
+            Field::new("instrument_id", DataType::UInt32, false),
+            Field::new("publisher_id", DataType::UInt16, false),
+        ]
+    }
+
+    fn append(&mut self, msg: &MboMsg) {
+        self.ts_event.append_value(msg.ts_event());
+        self.ts_recv.append_value(msg.ts_recv);
+        self.sequence.append_value(msg.sequence);
+        self.instrument_id.append_value(msg.instrument_id());
+        self.publisher_id.append_value(msg.publisher_id());
+    }
+
+    fn len(&self) -> usize {
+        self.ts_event.len()
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.ts_event.finish()),
+            Arc::new(self.ts_recv.finish()),
+            Arc::new(self.sequence.finish()),
+            Arc::new(self.instrument_id.finish()),
+            Arc::new(self.publisher_id.finish()),
+        ]
+    }
+}
+
+/// Price, float price, size and count of one side; null when the side is empty.
+struct SideColumns {
+    px: Int64Builder,
+    px_f64: Float64Builder,
+    sz: UInt32Builder,
+    ct: UInt32Builder,
+}
+
+impl SideColumns {
+    fn new() -> Self {
+        Self {
+            px: Int64Builder::new(),
+            px_f64: Float64Builder::new(),
+            sz: UInt32Builder::new(),
+            ct: UInt32Builder::new(),
+        }
+    }
+
+    fn fields(side: &str) -> Vec<Field> {
+        vec![
+            Field::new(format!("{}_px", side), DataType::Int64, true),
+            Field::new(format!("{}_px_f64", side), DataType::Float64, true),
+            Field::new(format!("{}_sz", side), DataType::UInt32, true),
+            Field::new(format!("{}_ct", side), DataType::UInt32, true),
+        ]
+    }
+
+    fn append(&mut self, level: Option<&PriceLevel>) {
+        self.px.append_option(level.map(|l| l.price.raw()));
+        self.px_f64.append_option(level.map(|l| l.price.to_f64()));
+        self.sz.append_option(level.map(|l| l.size));
+        self.ct.append_option(level.map(|l| l.count));
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.px.finish()),
+            Arc::new(self.px_f64.finish()),
+            Arc::new(self.sz.finish()),
+            Arc::new(self.ct.finish()),
+        ]
+    }
+}
+
+struct TableWriter {
+    schema: SchemaRef,
+    writer: ArrowWriter<File>,
+    rows: u64,
+}
+
+impl TableWriter {
+    fn create(path: &Path, fields: Vec<Field>) -> Result<Self, SinkError> {
+        let schema: SchemaRef = Arc::new(Schema::new(fields));
+        Ok(Self { writer: open_writer(path, schema.clone())?, schema, rows: 0 })
+    }
+
+    fn write(&mut self, columns: Vec<ArrayRef>) -> Result<(), SinkError> {
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.rows += batch.num_rows() as u64;
+        self.writer.write(&batch)?;
+        Ok(())
+    }
+
+    fn close(self) -> Result<u64, SinkError> {
+        self.writer.close()?;
+        Ok(self.rows)
+    }
+}
+
+// ============ BOOK LEVELS ============
+
+/// Long format: one row per (event, level) for the top N levels.
+pub struct BookTable {
+    out: TableWriter,
+    event: EventColumns,
+    level: UInt32Builder,
+    bid: SideColumns,
+    ask: SideColumns,
+}
+
+impl BookTable {
+    pub fn create(path: &Path) -> Result<Self, SinkError> {
+        let mut fields = EventColumns::fields();
+        fields.push(Field::new("level", DataType::UInt32, false));
+        fields.extend(SideColumns::fields("bid"));
+        fields.extend(SideColumns::fields("ask"));
+        Ok(Self {
+            out: TableWriter::create(path, fields)?,
+            event: EventColumns::new(),
+            level: UInt32Builder::new(),
+            bid: SideColumns::new(),
+            ask: SideColumns::new(),
+        })
+    }
+
+    pub fn append(&mut self, msg: &MboMsg, bids: &[PriceLevel], asks: &[PriceLevel]) -> Result<(), SinkError> {
+        for i in 0..bids.len().max(asks.len()) {
+            self.event.append(msg);
+            self.level.append_value(i as u32);
+            self.bid.append(bids.get(i));
+            self.ask.append(asks.get(i));
+        }
+        if self.event.len() >= BATCH_ROWS {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), SinkError> {
+        if self.event.len() == 0 {
+            return Ok(());
+        }
+        let mut columns = self.event.finish();
+        columns.push(Arc::new(self.level.finish()));
+        columns.extend(self.bid.finish());
+        columns.extend(self.ask.finish());
+        self.out.write(columns)
+    }
+
+    pub fn close(mut self) -> Result<u64, SinkError> {
+        self.flush()?;
+        self.out.close()
+    }
+}
+
+// ============ BBO CHANGES ============
+
+/// One row each time a book's best bid or offer (price, size or count) changes.
+pub struct BboTable {
+    out: TableWriter,
+    event: EventColumns,
+    bid: SideColumns,
+    ask: SideColumns,
+}
+
+impl BboTable {
+    pub fn create(path: &Path) -> Result<Self, SinkError> {
+        let mut fields = EventColumns::fields();
+        fields.extend(SideColumns::fields("bid"));
+        fields.extend(SideColumns::fields("ask"));
+        Ok(Self {
+            out: TableWriter::create(path, fields)?,
+            event: EventColumns::new(),
+            bid: SideColumns::new(),
+            ask: SideColumns::new(),
+        })
+    }
+
+    pub fn append(&mut self, msg: &MboMsg, bid: Option<&PriceLevel>, ask: Option<&PriceLevel>) -> Result<(), SinkError> {
+        self.event.append(msg);
+        self.bid.append(bid);
+        self.ask.append(ask);
+        if self.event.len() >= BATCH_ROWS {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), SinkError> {
+        if self.event.len() == 0 {
+            return Ok(());
+        }
+        let mut columns = self.event.finish();
+        columns.extend(self.bid.finish());
+        columns.extend(self.ask.finish());
+        self.out.write(columns)
+    }
+
+    pub fn close(mut self) -> Result<u64, SinkError> {
+        self.flush()?;
+        self.out.close()
+    }
+}
+
+// ============ TRADES ============
+
+/// One row per trade (`T`) message; `side` is the aggressor.
+pub struct TradesTable {
+    out: TableWriter,
+    event: EventColumns,
+    order_id: UInt64Builder,
+    side: StringBuilder,
+    price: Int64Builder,
+    price_f64: Float64Builder,
+    size: UInt32Builder,
+}
+
+impl TradesTable {
+    pub fn create(path: &Path) -> Result<Self, SinkError> {
+        let mut fields = EventColumns::fields();
+        fields.push(Field::new("order_id", DataType::UInt64, false));
+        fields.push(Field::new("side", DataType::Utf8, false));
+        fields.push(Field::new("price", DataType::Int64, false));
+        fields.push(Field::new("price_f64", DataType::Float64, false));
+        fields.push(Field::new("size", DataType::UInt32, false));
+        Ok(Self {
+            out: TableWriter::create(path, fields)?,
+            event: EventColumns::new(),
+            order_id: UInt64Builder::new(),
+            side: StringBuilder::new(),
+            price: Int64Builder::new(),
+            price_f64: Float64Builder::new(),
+            size: UInt32Builder::new(),
+        })
+    }
+
+    pub fn append(&mut self, msg: &MboMsg) -> Result<(), SinkError> {
+        self.event.append(msg);
+        self.order_id.append_value(msg.order_id);
+        self.side.append_value(msg.side_char().to_string());
+        self.price.append_value(msg.price);
+        self.price_f64.append_value(msg.px().map_or(f64::NAN, Price::to_f64));
+        self.size.append_value(msg.size);
+        if self.event.len() >= BATCH_ROWS {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), SinkError> {
+        if self.event.len() == 0 {
+            return Ok(());
+        }
+        let mut columns = self.event.finish();
+        columns.push(Arc::new(self.order_id.finish()));
+        columns.push(Arc::new(self.side.finish()));
+        columns.push(Arc::new(self.price.finish()));
+        columns.push(Arc::new(self.price_f64.finish()));
+        columns.push(Arc::new(self.size.finish()));
+        self.out.write(columns)
+    }
+
+    pub fn close(mut self) -> Result<u64, SinkError> {
+        self.flush()?;
+        self.out.close()
+    }
+}
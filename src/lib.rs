@@ -0,0 +1,9 @@
+pub mod analytics;
+pub mod dbn;
+pub mod error;
+pub mod export;
+pub mod logging;
+pub mod snapshot;
+pub mod types;
+
+pub use error::{Error, Result};
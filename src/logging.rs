@@ -0,0 +1,24 @@
+use tracing_subscriber::EnvFilter;
+
+/// Log line format on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human readable, one line per event.
+    Text,
+    /// One JSON object per line with span fields, for log aggregators.
+    Json,
+}
+
+/// Installs the global subscriber. `filter` uses `RUST_LOG` syntax
+/// (e.g. `info,hft_task::dbn::http=debug`); `RUST_LOG` wins when set.
+pub fn init(filter: &str, format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(filter));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().with_current_span(true).with_span_list(true).init(),
+    }
+}
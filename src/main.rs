@@ -1,16 +1,326 @@
-mod dbn;
-mod types;
+use clap::{Parser, Subcommand};
+use hft_task::analytics::bars::{BarConfig, BarSpec};
+use hft_task::analytics::microstructure::AnalyticsConfig;
+use hft_task::dbn;
+use hft_task::dbn::batch::{log_summary, run_batch, BatchConfig};
+use hft_task::dbn::checkpoint::CheckpointConfig;
+use hft_task::dbn::dbn_local::ServerConfig;
+use hft_task::dbn::recorder::RecorderConfig;
+use hft_task::dbn::source::SourceSpec;
+use hft_task::dbn::synth::{SynthConfig, SynthGenerator};
+use hft_task::export::{run_export, ExportConfig};
+use hft_task::logging::{self, LogFormat};
+use hft_task::snapshot::{Cadence, SinkFormat, SnapshotConfig};
+use hft_task::types::msg::SnapshotLayout;
+use hft_task::types::pretty::MessageFormat;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::info;
+
+#[derive(Parser)]
+#[command(name = "hft-task", about = "MBO replay, order book reconstruction and streaming")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Log filter in RUST_LOG syntax (overridden by RUST_LOG)
+    #[arg(long, global = true, default_value = "info")]
+    log_filter: String,
+    /// Log output format
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Stream a market data source to TCP and HTTP clients (default)
+    Serve(Box<ServeArgs>),
+    /// Run a local mock gateway that streams a DBN file over TCP
+    Gateway(GatewayArgs),
+    /// Write a synthetic MBO stream to a DBN file
+    Generate(GenerateArgs),
+    /// Replay a DBN file and export book, BBO and trade tables as Parquet
+    Export(ExportArgs),
+    /// Process a source as fast as possible without any servers
+    Batch(BatchArgs),
+}
+
+#[derive(Parser)]
+struct ServeArgs {
+    /// TCP address clients connect to
+    #[arg(long, default_value = "0.0.0.0:8080")]
+    addr: String,
+    /// HTTP API and WebSocket address
+    #[arg(long, default_value = "0.0.0.0:3001")]
+    http_addr: String,
+    /// DBN file path, tcp://host:port for a live gateway, or synth://key=value,... for generated flow
+    #[arg(long, default_value = "CLX5_mbo.dbn")]
+    source: SourceSpec,
+    /// Pause between records, in microseconds
+    #[arg(long, default_value_t = 0)]
+    sleep_us: u64,
+    #[command(flatten)]
+    snapshots: SnapshotArgs,
+    /// Record the broadcast stream to DBN files in this directory
+    #[arg(long)]
+    record_dir: Option<PathBuf>,
+    /// Rotate recordings after this many megabytes
+    #[arg(long, requires = "record_dir")]
+    record_max_mb: Option<u64>,
+    /// Rotate recordings after this many seconds
+    #[arg(long, requires = "record_dir")]
+    record_rotate_secs: Option<u64>,
+    /// Book levels used for imbalance and depth-weighted price
+    #[arg(long, default_value_t = 5)]
+    analytics_levels: usize,
+    /// Mid price returns in the realized volatility window
+    #[arg(long, default_value_t = 100)]
+    analytics_vol_window: usize,
+    /// Bar specs: durations (1s, 1m), tick:<n> or volume:<n>
+    #[arg(long, value_delimiter = ',', default_value = "1s,1m")]
+    bars: Vec<BarSpec>,
+    /// Keep per-order lifecycles for /api/orders/{order_id}
+    #[arg(long)]
+    order_history: bool,
+    /// Orders kept in the history store before the oldest are evicted
+    #[arg(long, default_value_t = 100_000)]
+    order_history_capacity: usize,
+    /// Recent messages kept for /api/messages
+    #[arg(long, default_value_t = 10_000)]
+    message_cache: usize,
+    /// Message format for TCP clients
+    #[arg(long, value_enum, default_value_t = MessageFormat::Compact)]
+    tcp_format: MessageFormat,
+    /// Levels per side tracked by the /ws/book_delta feed
+    #[arg(long, default_value_t = 10)]
+    delta_depth: usize,
+    /// TCP address streaming BBO changes (e.g. 0.0.0.0:8081)
+    #[arg(long)]
+    bbo_addr: Option<String>,
+    /// Write BBO changes to this JSON lines file
+    #[arg(long)]
+    bbo_file: Option<PathBuf>,
+    #[command(flatten)]
+    checkpoints: CheckpointArgs,
+}
+
+#[derive(clap::Args)]
+struct CheckpointArgs {
+    /// Save the full market state to this file for fast restarts
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// N records, wall:<dur> or event:<dur> between checkpoints
+    #[arg(long, requires = "checkpoint", default_value = "event:60s")]
+    checkpoint_every: Cadence,
+    /// Continue a file replay from a checkpoint instead of the start
+    #[arg(long)]
+    resume_from: Option<PathBuf>,
+}
+
+impl CheckpointArgs {
+    fn config(&self) -> Option<CheckpointConfig> {
+        self.checkpoint.clone().map(|path| CheckpointConfig { path, cadence: self.checkpoint_every })
+    }
+}
+
+#[derive(clap::Args)]
+struct SnapshotArgs {
+    /// Snapshot output format
+    #[arg(long = "snapshot-sink", value_enum, default_value_t = SinkFormat::Jsonl)]
+    format: SinkFormat,
+    /// Snapshot output path (defaults to snapshots.<ext>)
+    #[arg(long = "snapshot-path")]
+    path: Option<PathBuf>,
+    /// Book levels per snapshot
+    #[arg(long = "snapshot-depth", default_value_t = 50)]
+    depth: usize,
+    /// Pad snapshots to the full depth (dense) or drop empty levels (sparse)
+    #[arg(long = "snapshot-layout", value_enum, default_value_t = SnapshotLayout::Dense)]
+    layout: SnapshotLayout,
+    /// N records, wall:<dur> or event:<dur> (e.g. wall:500ms, event:1s)
+    #[arg(long = "snapshot-cadence", alias = "snapshot-every-n", default_value = "1")]
+    cadence: Cadence,
+    /// Write one file per instrument
+    #[arg(long = "snapshot-per-instrument")]
+    per_instrument: bool,
+    /// Rotate snapshot files after this many megabytes
+    #[arg(long = "snapshot-rotate-mb")]
+    rotate_mb: Option<u64>,
+    /// Rotate snapshot files after this many seconds
+    #[arg(long = "snapshot-rotate-secs")]
+    rotate_secs: Option<u64>,
+}
+
+impl From<SnapshotArgs> for SnapshotConfig {
+    fn from(args: SnapshotArgs) -> Self {
+        let defaults = SnapshotConfig::for_format(args.format);
+        SnapshotConfig {
+            path: args.path.unwrap_or(defaults.path),
+            depth: args.depth,
+            layout: args.layout,
+            cadence: args.cadence,
+            per_instrument: args.per_instrument,
+            rotate_bytes: args.rotate_mb.map(|mb| mb * 1024 * 1024),
+            rotate_after: args.rotate_secs.map(Duration::from_secs),
+            ..defaults
+        }
+    }
+}
+
+#[derive(Parser)]
+struct GatewayArgs {
+    /// TCP address the gateway listens on
+    #[arg(long, default_value = "0.0.0.0:9000")]
+    addr: String,
+    /// DBN file to stream to every client
+    #[arg(long, default_value = "CLX5_mbo.dbn")]
+    file: PathBuf,
+    /// Pause between records, in microseconds
+    #[arg(long, default_value_t = 0)]
+    sleep_us: u64,
+}
+
+#[derive(Parser)]
+struct BatchArgs {
+    /// DBN file path, tcp://host:port for a live gateway, or synth://key=value,... for generated flow
+    #[arg(long, default_value = "CLX5_mbo.dbn")]
+    source: SourceSpec,
+    #[command(flatten)]
+    snapshots: SnapshotArgs,
+    /// Write BBO changes to this JSON lines file
+    #[arg(long)]
+    bbo_file: Option<PathBuf>,
+    #[command(flatten)]
+    checkpoints: CheckpointArgs,
+}
+
+#[derive(Parser)]
+struct ExportArgs {
+    /// DBN file path, tcp://host:port for a live gateway, or synth://key=value,... for generated flow
+    #[arg(long, default_value = "CLX5_mbo.dbn")]
+    source: SourceSpec,
+    /// Directory for book.parquet, bbo.parquet and trades.parquet
+    #[arg(long, default_value = "export")]
+    out_dir: PathBuf,
+    /// Book levels per side in book.parquet
+    #[arg(long, default_value_t = 10)]
+    depth: usize,
+}
+
+#[derive(Parser)]
+struct GenerateArgs {
+    /// Output DBN file
+    #[arg(long, default_value = "synthetic_mbo.dbn")]
+    out: PathBuf,
+    /// Number of MBO records to write
+    #[arg(long, default_value_t = 1_000_000)]
+    messages: usize,
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    #[arg(long, default_value_t = 1)]
+    instruments: u32,
+    /// Mean events per second, per instrument
+    #[arg(long, default_value_t = 1_000.0)]
+    arrival_rate: f64,
+    /// Probability a non-trade event is a cancel
+    #[arg(long, default_value_t = 0.4)]
+    cancel_ratio: f64,
+    /// Probability a non-trade, non-cancel event is a modify
+    #[arg(long, default_value_t = 0.1)]
+    modify_ratio: f64,
+    /// Probability an event is a trade
+    #[arg(long, default_value_t = 0.05)]
+    trade_frequency: f64,
+    /// Mid price volatility in ticks per sqrt(second)
+    #[arg(long, default_value_t = 2.0)]
+    volatility: f64,
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("Starting HFT server...");
+async fn main() -> hft_task::Result<()> {
+    let cli = Cli::parse();
+    logging::init(&cli.log_filter, cli.log_format);
+
+    match cli.command.unwrap_or_else(|| Command::Serve(Box::new(ServeArgs::parse_from(["serve"])))) {
+        Command::Serve(args) => {
+            info!("starting HFT server");
 
-    // IMPORTANT: await the server future and return its Result
-    dbn::dbn_local::start_server(
-        "0.0.0.0:8080",
-        "CLX5_mbo.dbn".to_string(),
-        0,   // sleep_time
-        1    // snapshot_every_n
-    ).await
+            let recorder = args.record_dir.map(|dir| RecorderConfig {
+                max_bytes: args.record_max_mb.map(|mb| mb * 1024 * 1024),
+                max_age: args.record_rotate_secs.map(Duration::from_secs),
+                ..RecorderConfig::new(dir)
+            });
 
+            // IMPORTANT: await the server future and return its Result
+            dbn::dbn_local::start_server(ServerConfig {
+                addr: args.addr,
+                http_addr: args.http_addr,
+                source: args.source,
+                sleep_time: args.sleep_us,
+                snapshots: args.snapshots.into(),
+                recorder,
+                analytics: AnalyticsConfig {
+                    levels: args.analytics_levels,
+                    vol_window: args.analytics_vol_window,
+                },
+                bars: BarConfig { specs: args.bars, ..BarConfig::default() },
+                order_history: args.order_history.then_some(args.order_history_capacity),
+                message_cache: args.message_cache,
+                tcp_format: args.tcp_format,
+                delta_depth: args.delta_depth,
+                bbo_addr: args.bbo_addr,
+                bbo_file: args.bbo_file,
+                checkpoint: args.checkpoints.config(),
+                resume_from: args.checkpoints.resume_from,
+            }).await
+        }
+        Command::Gateway(args) => {
+            dbn::gateway::start_gateway(&args.addr, args.file, args.sleep_us).await
+        }
+        Command::Generate(args) => {
+            let cfg = SynthConfig {
+                seed: args.seed,
+                instruments: args.instruments,
+                arrival_rate: args.arrival_rate,
+                cancel_ratio: args.cancel_ratio,
+                modify_ratio: args.modify_ratio,
+                trade_frequency: args.trade_frequency,
+                volatility: args.volatility,
+                ..SynthConfig::default()
+            };
+            let written = SynthGenerator::new(cfg)
+                .with_limit(args.messages)
+                .write_dbn(&args.out)?;
+            info!(records = written, out = %args.out.display(), "wrote synthetic records");
+            Ok(())
+        }
+        Command::Batch(args) => {
+            let summary = run_batch(BatchConfig {
+                source: args.source,
+                snapshots: args.snapshots.into(),
+                bbo_file: args.bbo_file,
+                checkpoint: args.checkpoints.config(),
+                resume_from: args.checkpoints.resume_from,
+            })?;
+            log_summary(&summary);
+            Ok(())
+        }
+        Command::Export(args) => {
+            let cfg = ExportConfig {
+                source: args.source,
+                out_dir: args.out_dir,
+                depth: args.depth,
+            };
+            let summary = run_export(&cfg)?;
+            info!(
+                messages = summary.messages,
+                out_dir = %cfg.out_dir.display(),
+                book_rows = summary.book_rows,
+                bbo_rows = summary.bbo_rows,
+                trades = summary.trade_rows,
+                rejected = summary.rejected,
+                "export finished"
+            );
+            Ok(())
+        }
+    }
 }
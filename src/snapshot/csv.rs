@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use super::{BookSnapshot, SinkError, SnapshotSink};
+use crate::types::price::Price;
+
+fn fmt_px(px: Option<Price>) -> String {
+    px.map(|p| p.raw().to_string()).unwrap_or_default()
+}
+
+/// Wide CSV: one row per snapshot, six columns per level (`bid_px_00`, ...).
+/// Missing levels leave the price column empty.
+pub struct CsvSink {
+    writer: BufWriter<File>,
+    depth: usize,
+    bytes: u64,
+}
+
+impl CsvSink {
+    pub fn create(path: &Path, depth: usize) -> Result<Self, SinkError> {
+        let mut sink = Self { writer: BufWriter::new(File::create(path)?), depth, bytes: 0 };
+        let mut header = String::from("ts_event,ts_recv,sequence,instrument_id,publisher_id");
+        for i in 0..depth {
+            for col in ["bid_px", "bid_sz", "bid_ct", "ask_px", "ask_sz", "ask_ct"] {
+                header.push_str(&format!(",{}_{:02}", col, i));
+            }
+        }
+        header.push('\n');
+        sink.write_line(&header)?;
+        Ok(sink)
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), SinkError> {
+        self.writer.write_all(line.as_bytes())?;
+        self.bytes += line.len() as u64;
+        Ok(())
+    }
+}
+
+impl SnapshotSink for CsvSink {
+    fn write(&mut self, snap: &BookSnapshot<'_>) -> Result<(), SinkError> {
+        let mut line = format!(
+            "{},{},{},{},{}",
+            snap.trigger.ts_event(),
+            snap.trigger.ts_recv,
+            snap.trigger.sequence,
+            snap.instrument_id,
+            snap.publisher_id
+        );
+        for i in 0..self.depth {
+            let l = snap.levels.get(i).cloned().unwrap_or_default();
+            line.push_str(&format!(
+                ",{},{},{},{},{},{}",
+                fmt_px(l.bid_px), l.bid_sz, l.bid_ct, fmt_px(l.ask_px), l.ask_sz, l.ask_ct
+            ));
+        }
+        line.push('\n');
+        self.write_line(&line)
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    fn finish(&mut self) -> Result<(), SinkError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use super::{BookSnapshot, SinkError, SnapshotSink, TimedSnapshot};
+
+/// One JSON object per line, the original `snapshots.jsonl` format.
+pub struct JsonlSink {
+    writer: BufWriter<File>,
+    bytes: u64,
+}
+
+impl JsonlSink {
+    pub fn create(path: &Path) -> Result<Self, SinkError> {
+        Ok(Self { writer: BufWriter::new(File::create(path)?), bytes: 0 })
+    }
+}
+
+impl SnapshotSink for JsonlSink {
+    fn write(&mut self, snap: &BookSnapshot<'_>) -> Result<(), SinkError> {
+        let mut line = serde_json::to_vec(&TimedSnapshot::from(snap))?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        self.bytes += line.len() as u64;
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    fn finish(&mut self) -> Result<(), SinkError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use dbn::encode::dbn::Encoder;
+use dbn::encode::EncodeRecord;
+use dbn::record::{BidAskPair as DbnBidAskPair, Mbp10Msg, RecordHeader};
+use dbn::{rtype, FlagSet, Metadata, Schema};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use super::{BookSnapshot, SinkError, SnapshotSink};
+use crate::types::msg::UNDEF_PRICE;
+use crate::types::price::Price;
+
+const MBP10_DEPTH: usize = 10;
+
+/// DBN file of `Mbp10Msg` records, replayable by any DBN tooling.
+pub struct Mbp10Sink {
+    encoder: Encoder<BufWriter<File>>,
+    bytes: u64,
+}
+
+impl Mbp10Sink {
+    pub fn create(path: &Path, metadata: &Metadata) -> Result<Self, SinkError> {
+        let mut metadata = metadata.clone();
+        metadata.schema = Some(Schema::Mbp10);
+        metadata.end = None;
+        metadata.limit = None;
+        let encoder = Encoder::new(BufWriter::new(File::create(path)?), &metadata)?;
+        Ok(Self { encoder, bytes: 0 })
+    }
+}
+
+impl SnapshotSink for Mbp10Sink {
+    fn write(&mut self, snap: &BookSnapshot<'_>) -> Result<(), SinkError> {
+        let trigger = snap.trigger;
+        let mut levels: [DbnBidAskPair; MBP10_DEPTH] = Default::default();
+        for (dst, src) in levels.iter_mut().zip(snap.levels.iter()) {
+            *dst = DbnBidAskPair {
+                bid_px: src.bid_px.map_or(UNDEF_PRICE, Price::raw),
+                ask_px: src.ask_px.map_or(UNDEF_PRICE, Price::raw),
+                bid_sz: src.bid_sz,
+                ask_sz: src.ask_sz,
+                bid_ct: src.bid_ct,
+                ask_ct: src.ask_ct,
+            };
+        }
+        // `depth` is the level the triggering event touched, not the book depth.
+        let depth = snap
+            .levels
+            .iter()
+            .take(MBP10_DEPTH)
+            .position(|l| {
+                (trigger.is_bid() && l.bid_px.is_some_and(|px| px.raw() == trigger.price))
+                    || (trigger.is_ask() && l.ask_px.is_some_and(|px| px.raw() == trigger.price))
+            })
+            .unwrap_or(0) as u8;
+
+        let record = Mbp10Msg {
+            hd: RecordHeader::new::<Mbp10Msg>(
+                rtype::MBP_10,
+                snap.publisher_id,
+                snap.instrument_id,
+                trigger.ts_event(),
+            ),
+            price: trigger.price,
+            size: trigger.size,
+            action: trigger.action,
+            side: trigger.side,
+            flags: FlagSet::new(trigger.flags),
+            depth,
+            ts_recv: trigger.ts_recv,
+            ts_in_delta: trigger.ts_in_delta,
+            sequence: trigger.sequence,
+            levels,
+        };
+        self.encoder.encode_record(&record)?;
+        self.bytes += std::mem::size_of::<Mbp10Msg>() as u64;
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    fn finish(&mut self) -> Result<(), SinkError> {
+        self.encoder.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,562 @@
+pub mod csv;
+pub mod jsonl;
+pub mod mbp10;
+pub mod parquet;
+
+use dbn::Metadata;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use crate::types::msg::{BidAskPair, Market, MboMsg, SnapshotLayout};
+
+pub type SinkError = crate::Error;
+
+/// One book snapshot, taken right after `trigger` was applied.
+pub struct BookSnapshot<'a> {
+    pub trigger: &'a MboMsg,
+    pub instrument_id: u32,
+    pub publisher_id: u16,
+    pub levels: &'a [BidAskPair],
+}
+
+/// Row shape of the JSON snapshot feed.
+#[derive(Serialize)]
+pub struct TimedSnapshot<'a> {
+    pub ts_event: u64,
+    pub instrument_id: u32,
+    pub publisher_id: u16,
+    pub levels: &'a [BidAskPair],
+}
+
+impl<'a> From<&BookSnapshot<'a>> for TimedSnapshot<'a> {
+    fn from(snap: &BookSnapshot<'a>) -> Self {
+        Self {
+            ts_event: snap.trigger.ts_event(),
+            instrument_id: snap.instrument_id,
+            publisher_id: snap.publisher_id,
+            levels: snap.levels,
+        }
+    }
+}
+
+/// A destination for book snapshots. One sink owns one output file.
+pub trait SnapshotSink: Send {
+    fn write(&mut self, snap: &BookSnapshot<'_>) -> Result<(), SinkError>;
+
+    /// Approximate bytes written so far, used for size based rotation.
+    fn bytes_written(&self) -> u64;
+
+    /// Flushes buffered rows and closes the output.
+    fn finish(&mut self) -> Result<(), SinkError>;
+}
+
+// ============ CONFIG ============
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SinkFormat {
+    Jsonl,
+    Csv,
+    Parquet,
+    /// DBN MBP-10 records (depth fixed at 10).
+    DbnMbp10,
+    None,
+}
+
+impl SinkFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            SinkFormat::Jsonl => "jsonl",
+            SinkFormat::Csv => "csv",
+            SinkFormat::Parquet => "parquet",
+            SinkFormat::DbnMbp10 => "dbn",
+            SinkFormat::None => "",
+        }
+    }
+}
+
+/// How often a snapshot is taken, tracked per instrument for time cadences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cadence {
+    /// Every N messages across the whole stream.
+    Messages(usize),
+    /// At most once per interval of wall clock time.
+    WallTime(Duration),
+    /// At most once per interval of `ts_event` time.
+    EventTime(Duration),
+}
+
+impl FromStr for Cadence {
+    type Err = String;
+
+    /// Accepts `N` (messages), `wall:<dur>` or `event:<dur>` with `ns`, `us`, `ms` or `s` units.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(d) = s.strip_prefix("wall:") {
+            return parse_duration(d).map(Cadence::WallTime);
+        }
+        if let Some(d) = s.strip_prefix("event:") {
+            return parse_duration(d).map(Cadence::EventTime);
+        }
+        match s.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(Cadence::Messages(n)),
+            _ => Err(format!("invalid cadence '{}', expected N, wall:<dur> or event:<dur>", s)),
+        }
+    }
+}
+
+pub(crate) fn parse_duration(s: &str) -> Result<Duration, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: u64 = num.parse().map_err(|_| format!("invalid duration '{}'", s))?;
+    match unit {
+        "ns" => Ok(Duration::from_nanos(n)),
+        "us" => Ok(Duration::from_micros(n)),
+        "ms" => Ok(Duration::from_millis(n)),
+        "s" | "" => Ok(Duration::from_secs(n)),
+        "m" => Ok(Duration::from_secs(n * 60)),
+        _ => Err(format!("unknown duration unit in '{}'", s)),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SnapshotConfig {
+    pub format: SinkFormat,
+    /// Output path; per-instrument and rotation suffixes are added to its stem.
+    pub path: PathBuf,
+    pub depth: usize,
+    /// Dense pads to `depth` rows; sparse drops rows with no level on either side.
+    pub layout: SnapshotLayout,
+    pub cadence: Cadence,
+    pub per_instrument: bool,
+    pub rotate_bytes: Option<u64>,
+    pub rotate_after: Option<Duration>,
+}
+
+impl SnapshotConfig {
+    /// Defaults with the path set to `snapshots.<ext>` for `format`.
+    pub fn for_format(format: SinkFormat) -> Self {
+        Self {
+            format,
+            path: PathBuf::from(format!("snapshots.{}", format.extension())),
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            format: SinkFormat::Jsonl,
+            path: PathBuf::from("snapshots.jsonl"),
+            depth: 50,
+            layout: SnapshotLayout::Dense,
+            cadence: Cadence::Messages(1),
+            per_instrument: false,
+            rotate_bytes: None,
+            rotate_after: None,
+        }
+    }
+}
+
+// ============ WRITER ============
+
+struct RotatingSink {
+    base: PathBuf,
+    index: u32,
+    opened_at: Instant,
+    sink: Box<dyn SnapshotSink>,
+}
+
+/// Decides when to snapshot, and routes snapshots to per-instrument, rotating sinks.
+pub struct SnapshotWriter {
+    cfg: SnapshotConfig,
+    metadata: Metadata,
+    outputs: HashMap<Option<u32>, RotatingSink>,
+    last_wall: HashMap<u32, Instant>,
+    last_event: HashMap<u32, u64>,
+    seen: u64,
+}
+
+impl SnapshotWriter {
+    pub fn new(cfg: SnapshotConfig, metadata: Metadata) -> Self {
+        Self {
+            cfg,
+            metadata,
+            outputs: HashMap::new(),
+            last_wall: HashMap::new(),
+            last_event: HashMap::new(),
+            seen: 0,
+        }
+    }
+
+    /// Call after `msg` has been applied to `market`.
+    /// Returns whether a snapshot was written for this message.
+    pub fn on_message(&mut self, market: &Market, msg: &MboMsg) -> Result<bool, SinkError> {
+        let index = self.seen;
+        self.seen += 1;
+        if self.cfg.format == SinkFormat::None || !self.is_due(index, msg) {
+            return Ok(false);
+        }
+
+        let (inst, pub_id) = (msg.instrument_id(), msg.publisher_id());
+        let Some(book) = market.books.get(&inst).and_then(|b| b.get(&pub_id)) else {
+            return Ok(false);
+        };
+        let levels = book.get_snapshot(self.cfg.depth, self.cfg.layout);
+        let snap = BookSnapshot {
+            trigger: msg,
+            instrument_id: inst,
+            publisher_id: pub_id,
+            levels: &levels,
+        };
+
+        let key = self.cfg.per_instrument.then_some(inst);
+        self.rotate_if_needed(key)?;
+        let output = match self.outputs.entry(key) {
+            std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+            std::collections::hash_map::Entry::Vacant(e) => {
+                let base = base_path(&self.cfg, key);
+                let sink = open_sink(&self.cfg, &self.metadata, &with_index(&self.cfg, &base, 0))?;
+                e.insert(RotatingSink { base, index: 0, opened_at: Instant::now(), sink })
+            }
+        };
+        output.sink.write(&snap)?;
+        Ok(true)
+    }
+
+    pub fn finish(&mut self) -> Result<(), SinkError> {
+        for output in self.outputs.values_mut() {
+            output.sink.finish()?;
+        }
+        self.outputs.clear();
+        Ok(())
+    }
+
+    fn is_due(&mut self, index: u64, msg: &MboMsg) -> bool {
+        let inst = msg.instrument_id();
+        match self.cfg.cadence {
+            Cadence::Messages(n) => index.is_multiple_of(n.max(1) as u64),
+            Cadence::WallTime(every) => {
+                let now = Instant::now();
+                let due = self.last_wall.get(&inst).is_none_or(|t| now.duration_since(*t) >= every);
+                if due {
+                    self.last_wall.insert(inst, now);
+                }
+                due
+            }
+            Cadence::EventTime(every) => {
+                let ts = msg.ts_event();
+                let every = every.as_nanos() as u64;
+                let due = self.last_event.get(&inst).is_none_or(|t| ts.saturating_sub(*t) >= every);
+                if due {
+                    self.last_event.insert(inst, ts);
+                }
+                due
+            }
+        }
+    }
+
+    fn rotate_if_needed(&mut self, key: Option<u32>) -> Result<(), SinkError> {
+        let Some(output) = self.outputs.get_mut(&key) else {
+            return Ok(());
+        };
+        let too_big = self.cfg.rotate_bytes.is_some_and(|max| output.sink.bytes_written() >= max);
+        let too_old = self.cfg.rotate_after.is_some_and(|max| output.opened_at.elapsed() >= max);
+        if too_big || too_old {
+            output.sink.finish()?;
+            output.index += 1;
+            let path = with_index(&self.cfg, &output.base, output.index);
+            output.sink = open_sink(&self.cfg, &self.metadata, &path)?;
+            output.opened_at = Instant::now();
+        }
+        Ok(())
+    }
+}
+
+fn open_sink(cfg: &SnapshotConfig, metadata: &Metadata, path: &Path) -> Result<Box<dyn SnapshotSink>, SinkError> {
+    Ok(match cfg.format {
+        SinkFormat::Jsonl => Box::new(jsonl::JsonlSink::create(path)?),
+        SinkFormat::Csv => Box::new(csv::CsvSink::create(path, cfg.depth)?),
+        SinkFormat::Parquet => Box::new(parquet::ParquetSink::create(path, cfg.depth)?),
+        SinkFormat::DbnMbp10 => Box::new(mbp10::Mbp10Sink::create(path, metadata)?),
+        SinkFormat::None => unreachable!("no sink is opened for SinkFormat::None"),
+    })
+}
+
+/// `snapshots.jsonl` -> `snapshots_<instrument>.jsonl` when writing per instrument.
+fn base_path(cfg: &SnapshotConfig, key: Option<u32>) -> PathBuf {
+    match key {
+        Some(inst) => suffixed(&cfg.path, cfg.format, &inst.to_string()),
+        None => cfg.path.clone(),
+    }
+}
+
+/// Adds a `_00001` style suffix only when rotation is enabled.
+fn with_index(cfg: &SnapshotConfig, base: &Path, index: u32) -> PathBuf {
+    if cfg.rotate_bytes.is_none() && cfg.rotate_after.is_none() {
+        return base.to_path_buf();
+    }
+    suffixed(base, cfg.format, &format!("{:05}", index))
+}
+
+fn suffixed(path: &Path, format: SinkFormat, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("snapshots");
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_else(|| format.extension());
+    path.with_file_name(format!("{}_{}.{}", stem, suffix, ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbn::source::default_mbo_metadata;
+    use crate::types::msg::{RecordHeader, ACTION_ADD, FLAG_LAST, SIDE_BID};
+
+    const SEC: u64 = 1_000_000_000;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hft-task-snapshots-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn add(instrument_id: u32, ts_event: u64, order_id: u64) -> MboMsg {
+        MboMsg {
+            hd: RecordHeader { rtype: 0xA0, publisher_id: 1, instrument_id, ts_event },
+            order_id,
+            price: 100 * SEC as i64,
+            size: 1,
+            flags: FLAG_LAST,
+            channel_id: 0,
+            action: ACTION_ADD,
+            side: SIDE_BID,
+            ts_recv: ts_event,
+            ts_in_delta: 0,
+            sequence: order_id as u32,
+        }
+    }
+
+    /// Applies and offers each message to a JSONL writer; returns which were snapshotted.
+    fn run(cfg: SnapshotConfig, msgs: &[MboMsg]) -> Vec<bool> {
+        let mut writer = SnapshotWriter::new(cfg, default_mbo_metadata(0));
+        let mut market = Market::new();
+        let written = msgs
+            .iter()
+            .map(|msg| {
+                market.apply(msg).unwrap();
+                writer.on_message(&market, msg).unwrap()
+            })
+            .collect();
+        writer.finish().unwrap();
+        written
+    }
+
+    fn config(dir: &Path, cadence: Cadence) -> SnapshotConfig {
+        SnapshotConfig { path: dir.join("snapshots.jsonl"), depth: 1, cadence, ..SnapshotConfig::default() }
+    }
+
+    /// Files in `dir` with their line counts, by name.
+    fn files(dir: &Path) -> Vec<(String, usize)> {
+        let mut files: Vec<(String, usize)> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .map(|p| {
+                let lines = std::fs::read_to_string(&p).unwrap().lines().count();
+                (p.file_name().unwrap().to_string_lossy().into_owned(), lines)
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn message_cadence_counts_across_instruments() {
+        let dir = temp_dir("messages");
+        let msgs: Vec<MboMsg> = (0..7).map(|i| add(1 + (i % 2) as u32, i, i + 1)).collect();
+        let written = run(config(&dir, Cadence::Messages(3)), &msgs);
+        assert_eq!(written, [true, false, false, true, false, false, true]);
+        assert_eq!(files(&dir), [("snapshots.jsonl".to_string(), 3)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn time_cadences_are_tracked_per_instrument() {
+        let dir = temp_dir("event");
+        let msgs = [
+            add(1, 0, 1),
+            add(2, SEC / 2, 2),
+            add(1, SEC - 1, 3),
+            add(1, SEC, 4),
+            add(2, SEC, 5),
+            add(2, SEC + SEC / 2, 6),
+        ];
+        let written = run(config(&dir, Cadence::EventTime(Duration::from_secs(1))), &msgs);
+        assert_eq!(written, [true, true, false, true, false, true]);
+
+        // A long wall-clock interval only lets each instrument's first message through.
+        let written = run(config(&dir, Cadence::WallTime(Duration::from_secs(3600))), &msgs);
+        assert_eq!(written, [true, true, false, false, false, false]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn size_rotation_starts_a_new_file_once_the_limit_is_reached() {
+        let dir = temp_dir("size");
+        let msgs: Vec<MboMsg> = (0..5).map(|i| add(1, i, i + 1)).collect();
+        // Each JSONL row is well over 100 bytes, so every file holds two rows.
+        let cfg = SnapshotConfig { rotate_bytes: Some(150), ..config(&dir, Cadence::Messages(1)) };
+        run(cfg, &msgs);
+        assert_eq!(
+            files(&dir),
+            [
+                ("snapshots_00000.jsonl".to_string(), 2),
+                ("snapshots_00001.jsonl".to_string(), 2),
+                ("snapshots_00002.jsonl".to_string(), 1),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn time_rotation_starts_a_new_file_per_interval() {
+        let dir = temp_dir("time");
+        let msgs: Vec<MboMsg> = (0..3).map(|i| add(1, i, i + 1)).collect();
+        let cfg = SnapshotConfig { rotate_after: Some(Duration::ZERO), ..config(&dir, Cadence::Messages(1)) };
+        run(cfg, &msgs);
+        let names: Vec<String> = files(&dir).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["snapshots_00000.jsonl", "snapshots_00001.jsonl", "snapshots_00002.jsonl"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn per_instrument_files_rotate_independently() {
+        let dir = temp_dir("per-instrument");
+        let msgs = [add(7, 0, 1), add(9, 1, 2), add(7, 2, 3), add(7, 3, 4)];
+        let cfg = SnapshotConfig {
+            per_instrument: true,
+            rotate_bytes: Some(150),
+            ..config(&dir, Cadence::Messages(1))
+        };
+        run(cfg, &msgs);
+        assert_eq!(
+            files(&dir),
+            [
+                ("snapshots_7_00000.jsonl".to_string(), 2),
+                ("snapshots_7_00001.jsonl".to_string(), 1),
+                ("snapshots_9_00000.jsonl".to_string(), 1),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn output_names() {
+        let cfg = SnapshotConfig::for_format(SinkFormat::Parquet);
+        assert_eq!(base_path(&cfg, None), PathBuf::from("snapshots.parquet"));
+        assert_eq!(base_path(&cfg, Some(42)), PathBuf::from("snapshots_42.parquet"));
+        // No index without rotation.
+        assert_eq!(with_index(&cfg, Path::new("snapshots_42.parquet"), 3), PathBuf::from("snapshots_42.parquet"));
+
+        let rotating = SnapshotConfig { rotate_bytes: Some(1), ..cfg };
+        assert_eq!(
+            with_index(&rotating, Path::new("out/snapshots_42.parquet"), 3),
+            PathBuf::from("out/snapshots_42_00003.parquet")
+        );
+        // The format's extension fills in for a path without one.
+        let bare = SnapshotConfig { path: PathBuf::from("book"), ..rotating };
+        assert_eq!(base_path(&bare, Some(1)), PathBuf::from("book_1.parquet"));
+    }
+
+    /// One bid level and no asks, snapshotted at depth 3 in `format`.
+    fn shallow_book(name: &str, format: SinkFormat) -> (PathBuf, PathBuf) {
+        let dir = temp_dir(name);
+        let path = dir.join(format!("snapshots.{}", format.extension()));
+        let cfg = SnapshotConfig { format, path: path.clone(), depth: 3, ..SnapshotConfig::default() };
+        assert_eq!(run(cfg, &[add(1, 0, 1)]), [true]);
+        (dir, path)
+    }
+
+    #[test]
+    fn missing_levels_are_null_in_jsonl() {
+        let (dir, path) = shallow_book("null-jsonl", SinkFormat::Jsonl);
+        let row: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let levels = row["levels"].as_array().unwrap();
+        assert_eq!(levels.len(), 3);
+        assert_eq!(levels[0]["bid_px"], 100_000_000_000i64);
+        assert!(levels[0]["ask_px"].is_null());
+        for level in &levels[1..] {
+            assert!(level["bid_px"].is_null() && level["ask_px"].is_null(), "{}", level);
+            assert_eq!((&level["bid_sz"], &level["ask_ct"]), (&serde_json::json!(0), &serde_json::json!(0)));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_levels_are_empty_in_csv() {
+        let (dir, path) = shallow_book("null-csv", SinkFormat::Csv);
+        let text = std::fs::read_to_string(&path).unwrap();
+        let mut lines = text.lines();
+        let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+        let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+        let cell = |name: &str| row[header.iter().position(|h| *h == name).unwrap()];
+        assert_eq!(cell("bid_px_00"), "100000000000");
+        assert_eq!((cell("ask_px_00"), cell("ask_sz_00")), ("", "0"));
+        for i in 1..3 {
+            assert_eq!(cell(&format!("bid_px_{:02}", i)), "");
+            assert_eq!(cell(&format!("ask_px_{:02}", i)), "");
+            assert_eq!(cell(&format!("bid_ct_{:02}", i)), "0");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_levels_are_null_in_parquet() {
+        use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        use arrow_array::{Array, Int64Array, UInt32Array};
+
+        let (dir, path) = shallow_book("null-parquet", SinkFormat::Parquet);
+        let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<_> = reader.map(|b| b.unwrap()).collect();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 1);
+        let batch = &batches[0];
+        let column = |name: &str| batch.column_by_name(name).unwrap().clone();
+        let px = |name: &str| column(name).as_any().downcast_ref::<Int64Array>().unwrap().clone();
+        let sz = |name: &str| column(name).as_any().downcast_ref::<UInt32Array>().unwrap().value(0);
+        assert_eq!(px("bid_px_00").value(0), 100_000_000_000);
+        assert!(px("ask_px_00").is_null(0));
+        for i in 1..3 {
+            assert!(px(&format!("bid_px_{:02}", i)).is_null(0));
+            assert!(px(&format!("ask_px_{:02}", i)).is_null(0));
+            assert_eq!(sz(&format!("bid_sz_{:02}", i)), 0);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_levels_are_undefined_in_mbp10() {
+        use dbn::decode::{DbnDecoder, DecodeRecord};
+        use dbn::record::Mbp10Msg;
+        use crate::types::msg::UNDEF_PRICE;
+
+        // DBN has no null: `UNDEF_PRICE` is its null price, with zero size and count.
+        let (dir, path) = shallow_book("null-mbp10", SinkFormat::DbnMbp10);
+        let mut decoder = DbnDecoder::from_file(&path).unwrap();
+        let record = decoder.decode_record::<Mbp10Msg>().unwrap().unwrap().clone();
+        assert!(decoder.decode_record::<Mbp10Msg>().unwrap().is_none());
+        assert_eq!(record.levels[0].bid_px, 100_000_000_000);
+        assert_eq!(record.levels[0].ask_px, UNDEF_PRICE);
+        // Past the snapshot depth as well as past the book.
+        for level in &record.levels[1..] {
+            assert_eq!((level.bid_px, level.ask_px), (UNDEF_PRICE, UNDEF_PRICE));
+            assert_eq!((level.bid_sz, level.bid_ct, level.ask_sz, level.ask_ct), (0, 0, 0, 0));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
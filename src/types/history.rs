@@ -0,0 +1,195 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use crate::types::msg::{Action, MboMsg, Side};
+
+/// One step in an order's life.
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderEvent {
+    pub ts_event: u64,
+    pub ts_recv: u64,
+    pub sequence: u32,
+    pub action: Action,
+    pub side: Side,
+    pub price: i64,
+    pub size: u32,
+    pub flags: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderStatus {
+    Open,
+    Closed,
+}
+
+/// Full lifecycle of one order: add, modifies, fills and cancels in stream order.
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderHistory {
+    pub order_id: u64,
+    pub instrument_id: u32,
+    pub publisher_id: u16,
+    pub status: OrderStatus,
+    /// Size still resting after the last event.
+    pub remaining: u32,
+    pub filled: u64,
+    pub events: Vec<OrderEvent>,
+}
+
+/// Bounded store of order lifecycles; the oldest orders are evicted first.
+pub struct OrderHistoryStore {
+    capacity: usize,
+    orders: BTreeMap<(u64, u32, u16), OrderHistory>,
+    insertion: VecDeque<(u64, u32, u16)>,
+}
+
+impl OrderHistoryStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            orders: BTreeMap::new(),
+            insertion: VecDeque::new(),
+        }
+    }
+
+    /// Records add, modify, cancel and fill messages; other actions and unknown
+    /// codes are ignored.
+    pub fn record(&mut self, msg: &MboMsg) {
+        let (Ok(action), Ok(side)) = (msg.action(), msg.side()) else {
+            return;
+        };
+        if msg.order_id == 0 || !matches!(action, Action::Add | Action::Modify | Action::Cancel | Action::Fill) {
+            return;
+        }
+
+        let key = (msg.order_id, msg.instrument_id(), msg.publisher_id());
+        if !self.orders.contains_key(&key) {
+            self.insertion.push_back(key);
+            while self.insertion.len() > self.capacity {
+                if let Some(old) = self.insertion.pop_front() {
+                    self.orders.remove(&old);
+                }
+            }
+        }
+
+        let history = self.orders.entry(key).or_insert_with(|| OrderHistory {
+            order_id: msg.order_id,
+            instrument_id: msg.instrument_id(),
+            publisher_id: msg.publisher_id(),
+            status: OrderStatus::Open,
+            remaining: 0,
+            filled: 0,
+            events: Vec::new(),
+        });
+
+        match action {
+            Action::Add | Action::Modify => history.remaining = msg.size,
+            Action::Cancel => history.remaining = history.remaining.saturating_sub(msg.size),
+            _ => history.filled += u64::from(msg.size),
+        }
+        history.status = if history.remaining == 0 { OrderStatus::Closed } else { OrderStatus::Open };
+
+        history.events.push(OrderEvent {
+            ts_event: msg.ts_event(),
+            ts_recv: msg.ts_recv,
+            sequence: msg.sequence,
+            action,
+            side,
+            price: msg.price,
+            size: msg.size,
+            flags: msg.flags,
+        });
+    }
+
+    /// Every tracked order with this id, across instruments and publishers.
+    pub fn get(&self, order_id: u64) -> Vec<OrderHistory> {
+        self.orders
+            .range((order_id, u32::MIN, u16::MIN)..=(order_id, u32::MAX, u16::MAX))
+            .map(|(_, h)| h.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::c_char;
+    use crate::types::msg::{
+        RecordHeader, ACTION_ADD, ACTION_CANCEL, ACTION_CLEAR, ACTION_FILL, ACTION_MODIFY, ACTION_TRADE, FLAG_LAST,
+        SIDE_BID,
+    };
+
+    fn mbo(action: c_char, order_id: u64, size: u32, sequence: u32) -> MboMsg {
+        MboMsg {
+            hd: RecordHeader { rtype: 0xA0, publisher_id: 1, instrument_id: 1, ts_event: u64::from(sequence) },
+            order_id,
+            price: 100_000_000_000,
+            size,
+            flags: FLAG_LAST,
+            channel_id: 0,
+            action,
+            side: SIDE_BID,
+            ts_recv: 0,
+            ts_in_delta: 0,
+            sequence,
+        }
+    }
+
+    fn state(store: &OrderHistoryStore, order_id: u64) -> (OrderStatus, u32, u64, usize) {
+        let history = &store.get(order_id)[0];
+        (history.status, history.remaining, history.filled, history.events.len())
+    }
+
+    #[test]
+    fn tracks_remaining_filled_and_status() {
+        let mut store = OrderHistoryStore::new(10);
+        store.record(&mbo(ACTION_ADD, 7, 10, 1));
+        assert_eq!(state(&store, 7), (OrderStatus::Open, 10, 0, 1));
+
+        // A fill is counted; the cancel that follows it on the feed reduces the size.
+        store.record(&mbo(ACTION_FILL, 7, 3, 2));
+        store.record(&mbo(ACTION_CANCEL, 7, 3, 3));
+        assert_eq!(state(&store, 7), (OrderStatus::Open, 7, 3, 3));
+
+        store.record(&mbo(ACTION_MODIFY, 7, 4, 4));
+        assert_eq!(state(&store, 7), (OrderStatus::Open, 4, 3, 4));
+
+        store.record(&mbo(ACTION_CANCEL, 7, 4, 5));
+        assert_eq!(state(&store, 7), (OrderStatus::Closed, 0, 3, 5));
+        let actions: Vec<Action> = store.get(7)[0].events.iter().map(|e| e.action).collect();
+        assert_eq!(actions, [Action::Add, Action::Fill, Action::Cancel, Action::Modify, Action::Cancel]);
+    }
+
+    #[test]
+    fn ignores_other_actions_unknown_codes_and_order_zero() {
+        let mut store = OrderHistoryStore::new(10);
+        store.record(&mbo(ACTION_TRADE, 1, 1, 1));
+        store.record(&mbo(ACTION_CLEAR, 2, 0, 2));
+        store.record(&mbo(b'X' as c_char, 3, 1, 3));
+        store.record(&mbo(ACTION_ADD, 0, 1, 4));
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn evicts_the_oldest_order_at_capacity() {
+        let mut store = OrderHistoryStore::new(2);
+        store.record(&mbo(ACTION_ADD, 1, 1, 1));
+        store.record(&mbo(ACTION_ADD, 2, 1, 2));
+        // More events for a tracked order don't refresh or evict anything.
+        store.record(&mbo(ACTION_MODIFY, 1, 2, 3));
+        assert_eq!(store.len(), 2);
+
+        store.record(&mbo(ACTION_ADD, 3, 1, 4));
+        assert_eq!(store.len(), 2);
+        assert!(store.get(1).is_empty());
+        assert_eq!(store.get(2).len(), 1);
+        assert_eq!(store.get(3).len(), 1);
+    }
+}
@@ -1 +1,4 @@
-pub mod msg;
\ No newline at end of file
+pub mod history;
+pub mod msg;
+pub mod pretty;
+pub mod price;
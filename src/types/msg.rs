@@ -3,6 +3,8 @@ use std::collections::BTreeMap;
 use serde::{Serialize, Deserialize};
 use std::fs::File;
 use std::io::Write;
+use crate::error::Error;
+use crate::types::price::Price;
 // ============ CONSTANTS ============
 
 // Action codes
@@ -19,6 +21,108 @@ pub const SIDE_ASK: c_char = 65;        // 'A'
 pub const SIDE_BID: c_char = 66;        // 'B'
 pub const SIDE_NONE: c_char = 78;       // 'N'
 
+/// Typed MBO action; convert from the raw byte with `Action::try_from`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Add,
+    Cancel,
+    Modify,
+    Clear,
+    Trade,
+    Fill,
+    None,
+}
+
+impl TryFrom<c_char> for Action {
+    type Error = Error;
+
+    fn try_from(code: c_char) -> Result<Self, Error> {
+        match code {
+            ACTION_ADD => Ok(Action::Add),
+            ACTION_CANCEL => Ok(Action::Cancel),
+            ACTION_MODIFY => Ok(Action::Modify),
+            ACTION_CLEAR => Ok(Action::Clear),
+            ACTION_TRADE => Ok(Action::Trade),
+            ACTION_FILL => Ok(Action::Fill),
+            ACTION_NONE => Ok(Action::None),
+            _ => Err(Error::UnknownCode { field: "action", code }),
+        }
+    }
+}
+
+impl Action {
+    /// Lowercase name, as serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Action::Add => "add",
+            Action::Cancel => "cancel",
+            Action::Modify => "modify",
+            Action::Clear => "clear",
+            Action::Trade => "trade",
+            Action::Fill => "fill",
+            Action::None => "none",
+        }
+    }
+}
+
+impl From<Action> for c_char {
+    fn from(action: Action) -> Self {
+        match action {
+            Action::Add => ACTION_ADD,
+            Action::Cancel => ACTION_CANCEL,
+            Action::Modify => ACTION_MODIFY,
+            Action::Clear => ACTION_CLEAR,
+            Action::Trade => ACTION_TRADE,
+            Action::Fill => ACTION_FILL,
+            Action::None => ACTION_NONE,
+        }
+    }
+}
+
+/// Typed MBO side; convert from the raw byte with `Side::try_from`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Ask,
+    Bid,
+    None,
+}
+
+impl TryFrom<c_char> for Side {
+    type Error = Error;
+
+    fn try_from(code: c_char) -> Result<Self, Error> {
+        match code {
+            SIDE_ASK => Ok(Side::Ask),
+            SIDE_BID => Ok(Side::Bid),
+            SIDE_NONE => Ok(Side::None),
+            _ => Err(Error::UnknownCode { field: "side", code }),
+        }
+    }
+}
+
+impl Side {
+    /// Lowercase name, as serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Ask => "ask",
+            Side::Bid => "bid",
+            Side::None => "none",
+        }
+    }
+}
+
+impl From<Side> for c_char {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Ask => SIDE_ASK,
+            Side::Bid => SIDE_BID,
+            Side::None => SIDE_NONE,
+        }
+    }
+}
+
 // Flag bits
 pub const FLAG_LAST: u8 = 128;
 pub const FLAG_TOB: u8 = 64;
@@ -30,7 +134,7 @@ pub const UNDEF_PRICE: i64 = i64::MAX;
 // ============ MBO MESSAGE ============
 
 #[repr(C)]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub struct RecordHeader {
     pub rtype: u8,
     pub publisher_id: u16,
@@ -38,8 +142,10 @@ pub struct RecordHeader {
     pub ts_event: u64,
 }
 
+/// Plain-old-data copy of `dbn::record::MboMsg`; `Copy`, so it is passed by value
+/// into the book, cache and sinks instead of being cloned.
 #[repr(C)]
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub struct MboMsg {
     pub hd: RecordHeader,
     pub order_id: u64,
@@ -55,6 +161,8 @@ pub struct MboMsg {
 }
 
 impl MboMsg {
+    pub fn action(&self) -> Result<Action, Error> { Action::try_from(self.action) }
+    pub fn side(&self) -> Result<Side, Error> { Side::try_from(self.side) }
     pub fn action_char(&self) -> char { char::from(self.action as u8) }
     pub fn side_char(&self) -> char { char::from(self.side as u8) }
     pub fn is_add(&self) -> bool { self.action == ACTION_ADD }
@@ -67,65 +175,136 @@ impl MboMsg {
     pub fn is_ask(&self) -> bool { self.side == SIDE_ASK }
     pub fn is_last(&self) -> bool { self.flags & FLAG_LAST != 0 }
     pub fn is_tob(&self) -> bool { self.flags & FLAG_TOB != 0 }
-    pub fn price_f64(&self) -> f64 { self.price as f64 / FIXED_PRICE_SCALE }
+    /// `None` for `UNDEF_PRICE`.
+    pub fn px(&self) -> Option<Price> { Price::new(self.price) }
     pub fn is_undef_price(&self) -> bool { self.price == UNDEF_PRICE }
     pub fn instrument_id(&self) -> u32 { self.hd.instrument_id }
     pub fn publisher_id(&self) -> u16 { self.hd.publisher_id }
     pub fn ts_event(&self) -> u64 { self.hd.ts_event }
 }
 
+impl From<&dbn::record::MboMsg> for MboMsg {
+    fn from(msg: &dbn::record::MboMsg) -> Self {
+        Self {
+            hd: RecordHeader {
+                rtype: msg.hd.rtype,
+                publisher_id: msg.hd.publisher_id,
+                instrument_id: msg.hd.instrument_id,
+                ts_event: msg.hd.ts_event,
+            },
+            order_id: msg.order_id,
+            price: msg.price,
+            size: msg.size,
+            flags: msg.flags.raw(),
+            channel_id: msg.channel_id,
+            action: msg.action,
+            side: msg.side,
+            ts_recv: msg.ts_recv,
+            ts_in_delta: msg.ts_in_delta,
+            sequence: msg.sequence,
+        }
+    }
+}
+
+impl From<&MboMsg> for dbn::record::MboMsg {
+    fn from(msg: &MboMsg) -> Self {
+        Self {
+            hd: dbn::RecordHeader::new::<dbn::record::MboMsg>(
+                msg.hd.rtype,
+                msg.hd.publisher_id,
+                msg.hd.instrument_id,
+                msg.hd.ts_event,
+            ),
+            order_id: msg.order_id,
+            price: msg.price,
+            size: msg.size,
+            flags: dbn::FlagSet::new(msg.flags),
+            channel_id: msg.channel_id,
+            action: msg.action,
+            side: msg.side,
+            ts_recv: msg.ts_recv,
+            ts_in_delta: msg.ts_in_delta,
+            sequence: msg.sequence,
+        }
+    }
+}
+
 // ============ PRICE LEVEL ============
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PriceLevel {
-    pub price: i64,
+    pub price: Price,
     pub size: u32,
     pub count: u32,
 }
 
 impl PriceLevel {
-    pub fn new(price: i64) -> Self {
+    pub fn new(price: Price) -> Self {
         Self { price, size: 0, count: 0 }
     }
-    pub fn price_f64(&self) -> f64 {
-        self.price as f64 / FIXED_PRICE_SCALE
-    }
 }
 
 impl std::fmt::Display for PriceLevel {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:4} @ {:6.2} | {} order(s)", self.size, self.price_f64(), self.count)
+        write!(f, "{:4} @ {:6.2} | {} order(s)", self.size, self.price, self.count)
     }
 }
 
 // ============ BID ASK PAIR ============
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// One row of a depth snapshot. A side without a level at this depth has
+/// `None` price (`null` in JSON) and zero size and count.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct BidAskPair {
-    pub bid_px: i64,
+    pub bid_px: Option<Price>,
     pub bid_sz: u32,
     pub bid_ct: u32,
-    pub ask_px: i64,
+    pub ask_px: Option<Price>,
     pub ask_sz: u32,
     pub ask_ct: u32,
 }
 
 impl BidAskPair {
     pub fn new() -> Self { Self::default() }
-    pub fn bid_price_f64(&self) -> f64 { self.bid_px as f64 / FIXED_PRICE_SCALE }
-    pub fn ask_price_f64(&self) -> f64 { self.ask_px as f64 / FIXED_PRICE_SCALE }
+
+    pub fn from_levels(bid: Option<PriceLevel>, ask: Option<PriceLevel>) -> Self {
+        let mut pair = Self::new();
+        if let Some(bid) = bid {
+            pair.bid_px = Some(bid.price);
+            pair.bid_sz = bid.size;
+            pair.bid_ct = bid.count;
+        }
+        if let Some(ask) = ask {
+            pair.ask_px = Some(ask.price);
+            pair.ask_sz = ask.size;
+            pair.ask_ct = ask.count;
+        }
+        pair
+    }
+
+    pub fn is_empty(&self) -> bool { self.bid_px.is_none() && self.ask_px.is_none() }
+}
+
+/// Shape of `Book::get_snapshot` when the book is shallower than requested.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SnapshotLayout {
+    /// Always `level_count` rows, padding missing levels with empty pairs.
+    #[default]
+    Dense,
+    /// Only rows where at least one side has a level.
+    Sparse,
 }
 
 // ============ LEVEL ORDERS ============
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LevelOrders {
-    pub price: i64,
+    pub price: Price,
     pub orders: Vec<MboMsg>,
 }
 
 impl LevelOrders {
-    pub fn new(price: i64) -> Self {
+    pub fn new(price: Price) -> Self {
         Self { price, orders: Vec::new() }
     }
 
@@ -142,21 +321,131 @@ impl LevelOrders {
     }
 }
 
+// ============ L3 SNAPSHOT ============
+
+/// One resting order, as seen in an L3 snapshot.
+#[derive(Debug, Clone, Serialize)]
+pub struct L3Order {
+    pub order_id: u64,
+    /// Remaining size after partial cancels.
+    pub size: u32,
+    /// `ts_event` of the add or modify that put the order at its queue position.
+    pub ts_event: u64,
+}
+
+/// A price level with its orders in queue order, front of the queue first.
+#[derive(Debug, Clone, Serialize)]
+pub struct L3Level {
+    pub price: Price,
+    pub size: u32,
+    pub count: u32,
+    pub orders: Vec<L3Order>,
+}
+
+impl From<&LevelOrders> for L3Level {
+    fn from(level: &LevelOrders) -> Self {
+        let PriceLevel { price, size, count } = level.level();
+        let orders = level
+            .orders
+            .iter()
+            .map(|o| L3Order { order_id: o.order_id, size: o.size, ts_event: o.ts_event() })
+            .collect();
+        Self { price, size, count, orders }
+    }
+}
+
+/// Every resting order of one book, best levels first on both sides.
+#[derive(Debug, Clone, Serialize)]
+pub struct L3Snapshot {
+    pub instrument_id: u32,
+    pub publisher_id: u16,
+    pub bids: Vec<L3Level>,
+    pub asks: Vec<L3Level>,
+}
+
+// ============ LEVEL DELTAS ============
+
+/// How a level changed since the previous delta, MBP style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LevelAction {
+    /// Insert at `level`, shifting deeper levels down.
+    New,
+    /// Replace size and count at `level`.
+    Change,
+    /// Remove the level at `level`, shifting deeper levels up.
+    Delete,
+}
+
+/// One changed level. Deletes come first, deepest first, and carry the index the
+/// level had; news and changes follow in ascending index of the new book, so
+/// applying them in order keeps an index-based L2 copy in step.
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelDelta {
+    pub side: Side,
+    pub action: LevelAction,
+    pub level: usize,
+    pub price: Price,
+    /// Zero for deletes.
+    pub size: u32,
+    pub count: u32,
+}
+
+/// One message of the level delta feed: everything that changed in one book
+/// during the event ending with `sequence`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BookDelta {
+    pub ts_event: u64,
+    pub sequence: u32,
+    pub instrument_id: u32,
+    pub publisher_id: u16,
+    pub levels: Vec<LevelDelta>,
+}
+
+fn diff_levels(side: Side, old: &[PriceLevel], new: &[PriceLevel], out: &mut Vec<LevelDelta>) {
+    for (level, prev) in old.iter().enumerate().rev() {
+        if !new.iter().any(|l| l.price == prev.price) {
+            out.push(LevelDelta { side, action: LevelAction::Delete, level, price: prev.price, size: 0, count: 0 });
+        }
+    }
+    for (level, cur) in new.iter().enumerate() {
+        let action = match old.iter().find(|l| l.price == cur.price) {
+            None => LevelAction::New,
+            Some(prev) if prev != cur => LevelAction::Change,
+            Some(_) => continue,
+        };
+        out.push(LevelDelta { side, action, level, price: cur.price, size: cur.size, count: cur.count });
+    }
+}
+
 // ============ BOOK ============
 
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Book {
     pub orders_by_id: BTreeMap<u64, MboMsg>,
-    pub offers: BTreeMap<i64, LevelOrders>,
-    pub bids: BTreeMap<i64, LevelOrders>,
+    pub offers: BTreeMap<Price, LevelOrders>,
+    pub bids: BTreeMap<Price, LevelOrders>,
+    /// Top levels as of the last `level_deltas` call. Not checkpointed, so the
+    /// first deltas after a restore carry every level.
+    #[serde(skip)]
+    published: (Vec<PriceLevel>, Vec<PriceLevel>),
 }
 
 impl Book {
     pub fn new() -> Self {
-        Self {
-            orders_by_id: BTreeMap::new(),
-            offers: BTreeMap::new(),
-            bids: BTreeMap::new(),
-        }
+        Self::default()
+    }
+
+    /// Levels within the top `depth` that changed since the previous call, which
+    /// is meant to happen once per `F_LAST` event. Levels pushed below `depth`
+    /// are reported as deletes.
+    pub fn level_deltas(&mut self, depth: usize) -> Vec<LevelDelta> {
+        let (bids, asks) = self.get_depth(depth);
+        let mut deltas = Vec::new();
+        diff_levels(Side::Bid, &self.published.0, &bids, &mut deltas);
+        diff_levels(Side::Ask, &self.published.1, &asks, &mut deltas);
+        self.published = (bids, asks);
+        deltas
     }
 
     pub fn bbo(&self) -> (Option<PriceLevel>, Option<PriceLevel>) {
@@ -175,21 +464,14 @@ impl Book {
         self.orders_by_id.get(&id)
     }
 
-    pub fn get_snapshot(&self, level_count: usize) -> Vec<BidAskPair> {
-        (0..level_count).map(|i| {
-            let mut pair = BidAskPair::new();
-            if let Some(bid) = self.get_bid_level(i) {
-                pair.bid_px = bid.price;
-                pair.bid_sz = bid.size;
-                pair.bid_ct = bid.count;
-            }
-            if let Some(ask) = self.get_ask_level(i) {
-                pair.ask_px = ask.price;
-                pair.ask_sz = ask.size;
-                pair.ask_ct = ask.count;
-            }
-            pair
-        }).collect()
+    pub fn get_snapshot(&self, level_count: usize, layout: SnapshotLayout) -> Vec<BidAskPair> {
+        let rows = match layout {
+            SnapshotLayout::Dense => level_count,
+            SnapshotLayout::Sparse => level_count.min(self.bids.len().max(self.offers.len())),
+        };
+        let mut bids = self.bids.values().rev().map(|l| l.level());
+        let mut asks = self.offers.values().map(|l| l.level());
+        (0..rows).map(|_| BidAskPair::from_levels(bids.next(), asks.next())).collect()
     }
 
     pub fn get_depth(&self, levels: usize) -> (Vec<PriceLevel>, Vec<PriceLevel>) {
@@ -198,33 +480,61 @@ impl Book {
         (bids, asks)
     }
 
-    pub fn apply(&mut self, mbo: &MboMsg) {
-        // Trade, Fill, None: no change
-        if mbo.is_trade() || mbo.is_fill() || mbo.action == ACTION_NONE {
-            return;
-        }
-        // Clear book
-        if mbo.is_clear() {
-            self.clear();
-            return;
-        }
-        // Side must be A or B
-        if !mbo.is_ask() && !mbo.is_bid() {
-            return;
+    /// Per-order view of the best `levels` levels per side, or the whole book.
+    pub fn get_l3(&self, levels: Option<usize>) -> (Vec<L3Level>, Vec<L3Level>) {
+        let take = levels.unwrap_or(usize::MAX);
+        let bids = self.bids.values().rev().take(take).map(L3Level::from).collect();
+        let asks = self.offers.values().take(take).map(L3Level::from).collect();
+        (bids, asks)
+    }
+
+    /// Applies one message. Unknown action/side codes, adds, cancels or modifies
+    /// without a side, and non-TOB adds or modifies without a price are rejected
+    /// and leave the book untouched.
+    pub fn apply(&mut self, mbo: &MboMsg) -> Result<(), Error> {
+        let action = mbo.action()?;
+        let side = mbo.side()?;
+        match action {
+            // Trade, Fill, None: no change
+            Action::Trade | Action::Fill | Action::None => return Ok(()),
+            Action::Clear => {
+                self.clear();
+                return Ok(());
+            }
+            Action::Add | Action::Cancel | Action::Modify => {}
         }
-        // UNDEF_PRICE with TOB: clear side
-        if mbo.is_undef_price() && mbo.is_tob() {
-            self.side_levels_mut(mbo.side).clear();
-            return;
+        if side == Side::None {
+            return Err(Error::InvalidRecord(format!(
+                "{:?} without a side for order {}",
+                action, mbo.order_id
+            )));
         }
+        let Some(price) = mbo.px() else {
+            // Undefined price with TOB: clear side
+            if mbo.is_tob() {
+                if let Some(levels) = self.side_levels_mut(side) {
+                    levels.clear();
+                }
+                return Ok(());
+            }
+            // A cancel only needs the resting order's own price
+            if action == Action::Cancel {
+                self.cancel(mbo);
+                return Ok(());
+            }
+            return Err(Error::InvalidRecord(format!(
+                "{:?} with an undefined price for order {}",
+                action, mbo.order_id
+            )));
+        };
 
-        if mbo.is_add() {
-            self.add(mbo);
-        } else if mbo.is_cancel() {
-            self.cancel(mbo);
-        } else if mbo.is_modify() {
-            self.modify(mbo);
+        match action {
+            Action::Add => self.add(mbo, side, price),
+            Action::Cancel => self.cancel(mbo),
+            Action::Modify => self.modify(mbo, side, price),
+            _ => {}
         }
+        Ok(())
     }
 
     fn clear(&mut self) {
@@ -233,72 +543,107 @@ impl Book {
         self.bids.clear();
     }
 
-    fn add(&mut self, mbo: &MboMsg) {
+    fn add(&mut self, mbo: &MboMsg, side: Side, price: Price) {
+        let Some(levels) = self.side_levels_mut(side) else {
+            return;
+        };
         if mbo.is_tob() {
-            let levels = self.side_levels_mut(mbo.side);
             levels.clear();
-            levels.insert(mbo.price, LevelOrders { price: mbo.price, orders: vec![mbo.clone()] });
+            levels.insert(price, LevelOrders { price, orders: vec![*mbo] });
         } else {
-            self.orders_by_id.insert(mbo.order_id, mbo.clone());
-            let level = self.get_or_insert_level(mbo.price, mbo.side);
-            level.orders.push(mbo.clone());
+            levels
+                .entry(price)
+                .or_insert_with(|| LevelOrders::new(price))
+                .orders
+                .push(*mbo);
+            self.orders_by_id.insert(mbo.order_id, *mbo);
         }
     }
 
     fn cancel(&mut self, mbo: &MboMsg) {
         if let Some(order) = self.orders_by_id.get_mut(&mbo.order_id) {
-            let price = order.price;
-            let side = order.side;
+            let price = order.px();
+            let side = order.side().unwrap_or(Side::None);
 
             if order.size >= mbo.size {
                 order.size -= mbo.size;
             }
 
-            if order.size == 0 {
+            let remaining = order.size;
+            if remaining == 0 {
                 self.orders_by_id.remove(&mbo.order_id);
-                if let Some(level) = self.side_levels_mut(side).get_mut(&price) {
-                    level.orders.retain(|o| o.order_id != mbo.order_id);
-                    if level.is_empty() {
-                        self.side_levels_mut(side).remove(&price);
-                    }
-                }
+            }
+            let Some(price) = price else {
+                return;
+            };
+            if remaining == 0 {
+                self.remove_from_level(side, price, mbo.order_id);
+            } else {
+                self.resize_in_level(side, price, mbo.order_id, remaining);
             }
         }
     }
 
-    fn modify(&mut self, mbo: &MboMsg) {
-        if let Some(order) = self.orders_by_id.get(&mbo.order_id).cloned() {
-            let old_price = order.price;
-            let side = order.side;
-
-            if let Some(level) = self.side_levels_mut(side).get_mut(&old_price) {
-                level.orders.retain(|o| o.order_id != mbo.order_id);
-                if level.is_empty() {
-                    self.side_levels_mut(side).remove(&old_price);
-                }
+    fn modify(&mut self, mbo: &MboMsg, side: Side, price: Price) {
+        if let Some(order) = self.orders_by_id.get(&mbo.order_id).copied() {
+            let old_side = order.side().unwrap_or(Side::None);
+            if let Some(old_price) = order.px() {
+                self.remove_from_level(old_side, old_price, mbo.order_id);
             }
 
-            let new_level = self.get_or_insert_level(mbo.price, mbo.side);
-            new_level.orders.push(mbo.clone());
-            self.orders_by_id.insert(mbo.order_id, mbo.clone());
+            if let Some(levels) = self.side_levels_mut(side) {
+                levels
+                    .entry(price)
+                    .or_insert_with(|| LevelOrders::new(price))
+                    .orders
+                    .push(*mbo);
+                self.orders_by_id.insert(mbo.order_id, *mbo);
+            }
         } else {
-            self.add(mbo);
+            self.add(mbo, side, price);
         }
     }
 
-    fn side_levels_mut(&mut self, side: c_char) -> &mut BTreeMap<i64, LevelOrders> {
-        if side == SIDE_ASK { &mut self.offers } else { &mut self.bids }
+    fn remove_from_level(&mut self, side: Side, price: Price, order_id: u64) {
+        let Some(levels) = self.side_levels_mut(side) else {
+            return;
+        };
+        if let Some(level) = levels.get_mut(&price) {
+            level.orders.retain(|o| o.order_id != order_id);
+            if level.is_empty() {
+                levels.remove(&price);
+            }
+        }
     }
 
-    fn get_or_insert_level(&mut self, price: i64, side: c_char) -> &mut LevelOrders {
-        self.side_levels_mut(side).entry(price).or_insert_with(|| LevelOrders::new(price))
+    /// Keeps the queued copy of a partially cancelled order in step with `orders_by_id`.
+    fn resize_in_level(&mut self, side: Side, price: Price, order_id: u64, size: u32) {
+        let order = self
+            .side_levels_mut(side)
+            .and_then(|levels| levels.get_mut(&price))
+            .and_then(|level| level.orders.iter_mut().find(|o| o.order_id == order_id));
+        if let Some(order) = order {
+            order.size = size;
+        }
+    }
+
+    /// Only `Ask` and `Bid` have levels; there is no fallback side.
+    fn side_levels_mut(&mut self, side: Side) -> Option<&mut BTreeMap<Price, LevelOrders>> {
+        match side {
+            Side::Ask => Some(&mut self.offers),
+            Side::Bid => Some(&mut self.bids),
+            Side::None => None,
+        }
     }
 }
 
 // ============ MARKET ============
 
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Market {
     pub books: BTreeMap<u32, BTreeMap<u16, Book>>,
+    /// Minimum price increment per instrument, from instrument definitions.
+    pub tick_sizes: BTreeMap<u32, Price>,
 }
 
 #[derive(Serialize)]
@@ -310,23 +655,51 @@ struct BookSnapshotJson {
 
 impl Market {
     pub fn new() -> Self {
-        Self { books: BTreeMap::new() }
+        Self { books: BTreeMap::new(), tick_sizes: BTreeMap::new() }
+    }
+
+    /// Records the tick size of a definition; definitions without one are ignored.
+    pub fn on_definition(&mut self, def: &dbn::record::InstrumentDefMsg) {
+        if let Some(tick) = Price::tick_size(def) {
+            self.tick_sizes.insert(def.hd.instrument_id, tick);
+        }
+    }
+
+    pub fn tick_size(&self, instrument_id: u32) -> Option<Price> {
+        self.tick_sizes.get(&instrument_id).copied()
     }
 
     pub fn get_book(&mut self, instrument_id: u32, publisher_id: u16) -> &mut Book {
         self.books
             .entry(instrument_id)
-            .or_insert_with(BTreeMap::new)
+            .or_default()
             .entry(publisher_id)
-            .or_insert_with(Book::new)
+            .or_default()
+    }
+
+    /// L3 view of one book; `None` if the book has never been seen.
+    pub fn get_l3_snapshot(&self, instrument_id: u32, publisher_id: u16, levels: Option<usize>) -> Option<L3Snapshot> {
+        let book = self.books.get(&instrument_id)?.get(&publisher_id)?;
+        let (bids, asks) = book.get_l3(levels);
+        Some(L3Snapshot { instrument_id, publisher_id, bids, asks })
     }
 
     pub fn bbo(&mut self, instrument_id: u32, publisher_id: u16) -> (Option<PriceLevel>, Option<PriceLevel>) {
         self.get_book(instrument_id, publisher_id).bbo()
     }
 
-    pub fn apply(&mut self, mbo: &MboMsg) {
-        self.get_book(mbo.instrument_id(), mbo.publisher_id()).apply(mbo);
+    /// Applies one message to its book. Once a definition has set the
+    /// instrument's tick size, adds and modifies off that tick are rejected.
+    pub fn apply(&mut self, mbo: &MboMsg) -> Result<(), Error> {
+        if let (Some(tick), Some(price)) = (self.tick_size(mbo.instrument_id()), mbo.px()) {
+            if matches!(mbo.action(), Ok(Action::Add | Action::Modify)) && !price.is_on_tick(tick) {
+                return Err(Error::InvalidRecord(format!(
+                    "price {} of order {} is not a multiple of tick size {}",
+                    price, mbo.order_id, tick
+                )));
+            }
+        }
+        self.get_book(mbo.instrument_id(), mbo.publisher_id()).apply(mbo)
     }
 
     pub fn aggregated_bbo(&self, instrument_id: u32) -> (Option<PriceLevel>, Option<PriceLevel>) {
@@ -374,17 +747,18 @@ impl Market {
         instrument_id: u32,
         publisher_id: u16,
         levels: usize,
+        layout: SnapshotLayout,
         path: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> crate::Result<()> {
         let books_for_instr = self.books.get(&instrument_id)
-            .ok_or("instrument not found")?;
+            .ok_or_else(|| Error::NotFound(format!("instrument {}", instrument_id)))?;
         let book = books_for_instr.get(&publisher_id)
-            .ok_or("publisher not found")?;
+            .ok_or_else(|| Error::NotFound(format!("publisher {} for instrument {}", publisher_id, instrument_id)))?;
 
         let snapshot = BookSnapshotJson {
             instrument_id,
             publisher_id,
-            levels: book.get_snapshot(levels),
+            levels: book.get_snapshot(levels, layout),
         };
 
         let mut file = File::create(path)?;
@@ -393,3 +767,311 @@ impl Market {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn px(units: i64) -> i64 {
+        units * 1_000_000_000
+    }
+
+    fn mbo(action: c_char, side: c_char, price: i64, size: u32, order_id: u64) -> MboMsg {
+        MboMsg {
+            hd: RecordHeader { rtype: 0xA0, publisher_id: 1, instrument_id: 1, ts_event: 0 },
+            order_id,
+            price,
+            size,
+            flags: FLAG_LAST,
+            channel_id: 0,
+            action,
+            side,
+            ts_recv: 0,
+            ts_in_delta: 0,
+            sequence: 0,
+        }
+    }
+
+    fn seeded_book() -> Book {
+        let mut book = Book::new();
+        book.apply(&mbo(ACTION_ADD, SIDE_BID, px(100), 5, 1)).unwrap();
+        book.apply(&mbo(ACTION_ADD, SIDE_ASK, px(101), 7, 2)).unwrap();
+        book
+    }
+
+    fn assert_rejected(msg: MboMsg) -> Error {
+        let mut book = seeded_book();
+        let before = book.get_l3(None);
+        let err = book.apply(&msg).unwrap_err();
+        let after = book.get_l3(None);
+        assert_eq!(serde_json::to_value(before).unwrap(), serde_json::to_value(after).unwrap());
+        assert_eq!(book.orders_by_id.len(), 2);
+        err
+    }
+
+    #[test]
+    fn apply_rejects_unknown_action() {
+        let err = assert_rejected(mbo(b'X' as c_char, SIDE_BID, px(100), 1, 3));
+        assert!(matches!(err, Error::UnknownCode { field: "action", code } if code == b'X' as c_char));
+    }
+
+    #[test]
+    fn apply_rejects_unknown_side() {
+        let err = assert_rejected(mbo(ACTION_ADD, b'Z' as c_char, px(100), 1, 3));
+        assert!(matches!(err, Error::UnknownCode { field: "side", .. }));
+        // Even actions that don't touch the book are rejected on a bad side code.
+        let err = assert_rejected(mbo(ACTION_TRADE, b'Z' as c_char, px(100), 1, 3));
+        assert!(matches!(err, Error::UnknownCode { field: "side", .. }));
+    }
+
+    #[test]
+    fn apply_rejects_side_none_on_order_actions() {
+        for action in [ACTION_ADD, ACTION_CANCEL, ACTION_MODIFY] {
+            let err = assert_rejected(mbo(action, SIDE_NONE, px(100), 5, 1));
+            assert!(matches!(err, Error::InvalidRecord(_)), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn apply_rejects_undefined_price_on_non_tob_add() {
+        let err = assert_rejected(mbo(ACTION_ADD, SIDE_BID, UNDEF_PRICE, 1, 3));
+        assert!(matches!(err, Error::InvalidRecord(_)));
+        let err = assert_rejected(mbo(ACTION_MODIFY, SIDE_BID, UNDEF_PRICE, 1, 1));
+        assert!(matches!(err, Error::InvalidRecord(_)));
+    }
+
+    #[test]
+    fn apply_accepts_side_none_trade_and_undefined_price_cancel() {
+        let mut book = seeded_book();
+        book.apply(&mbo(ACTION_TRADE, SIDE_NONE, px(100), 1, 0)).unwrap();
+        book.apply(&mbo(ACTION_CANCEL, SIDE_BID, UNDEF_PRICE, 5, 1)).unwrap();
+        assert!(book.bids.is_empty());
+        assert_eq!(book.orders_by_id.len(), 1);
+    }
+
+    #[test]
+    fn market_rejects_off_tick_prices_once_tick_is_known() {
+        let mut market = Market::new();
+        let off_tick = mbo(ACTION_ADD, SIDE_BID, px(100) + 5_000_000, 1, 1);
+        market.apply(&off_tick).unwrap();
+
+        let def = dbn::record::InstrumentDefMsg {
+            hd: dbn::RecordHeader::new::<dbn::record::InstrumentDefMsg>(dbn::rtype::INSTRUMENT_DEF, 1, 1, 0),
+            min_price_increment: 10_000_000,
+            ..Default::default()
+        };
+        market.on_definition(&def);
+        assert_eq!(market.tick_size(1), Price::new(10_000_000));
+
+        let err = market.apply(&mbo(ACTION_ADD, SIDE_BID, px(100) + 5_000_000, 1, 2)).unwrap_err();
+        assert!(matches!(err, Error::InvalidRecord(_)));
+        market.apply(&mbo(ACTION_ADD, SIDE_BID, px(100) + 10_000_000, 1, 3)).unwrap();
+        // Resting off-tick orders can still be cancelled.
+        market.apply(&mbo(ACTION_CANCEL, SIDE_BID, px(100) + 5_000_000, 1, 1)).unwrap();
+        assert_eq!(market.books[&1][&1].orders_by_id.len(), 1);
+    }
+
+    // --- level deltas ---
+
+    const DEPTH: usize = 3;
+
+    fn side_view(rows: &[BidAskPair], side: Side) -> Vec<PriceLevel> {
+        rows.iter()
+            .filter_map(|row| match side {
+                Side::Bid => row.bid_px.map(|price| PriceLevel { price, size: row.bid_sz, count: row.bid_ct }),
+                _ => row.ask_px.map(|price| PriceLevel { price, size: row.ask_sz, count: row.ask_ct }),
+            })
+            .collect()
+    }
+
+    /// What a consumer holding only the previous dense snapshot rebuilds from the deltas.
+    fn replay(prev: &[BidAskPair], deltas: &[LevelDelta]) -> Vec<BidAskPair> {
+        let mut bids = side_view(prev, Side::Bid);
+        let mut asks = side_view(prev, Side::Ask);
+        for delta in deltas {
+            let levels = if delta.side == Side::Bid { &mut bids } else { &mut asks };
+            let level = PriceLevel { price: delta.price, size: delta.size, count: delta.count };
+            match delta.action {
+                LevelAction::Delete => {
+                    assert_eq!(levels[delta.level].price, delta.price, "delete at wrong index: {:?}", delta);
+                    levels.remove(delta.level);
+                }
+                LevelAction::New => levels.insert(delta.level, level),
+                LevelAction::Change => {
+                    assert_eq!(levels[delta.level].price, delta.price, "change at wrong index: {:?}", delta);
+                    levels[delta.level] = level;
+                }
+            }
+        }
+        let (mut bids, mut asks) = (bids.into_iter(), asks.into_iter());
+        (0..prev.len()).map(|_| BidAskPair::from_levels(bids.next(), asks.next())).collect()
+    }
+
+    /// Applies one event, then checks its deltas carry the book's previous
+    /// snapshot to the new one. Returns the deltas.
+    fn step(book: &mut Book, msgs: &[MboMsg]) -> Vec<LevelDelta> {
+        let prev = book.get_snapshot(DEPTH, SnapshotLayout::Dense);
+        for msg in msgs {
+            book.apply(msg).unwrap();
+        }
+        let deltas = book.level_deltas(DEPTH);
+        assert_eq!(replay(&prev, &deltas), book.get_snapshot(DEPTH, SnapshotLayout::Dense), "deltas {:?}", deltas);
+        deltas
+    }
+
+    fn actions(deltas: &[LevelDelta]) -> Vec<(LevelAction, usize, i64)> {
+        deltas.iter().map(|d| (d.action, d.level, d.price.raw())).collect()
+    }
+
+    fn deltas_book() -> Book {
+        let mut book = Book::new();
+        step(
+            &mut book,
+            &[
+                mbo(ACTION_ADD, SIDE_BID, px(100), 5, 1),
+                mbo(ACTION_ADD, SIDE_BID, px(99), 5, 2),
+                mbo(ACTION_ADD, SIDE_BID, px(98), 5, 3),
+                mbo(ACTION_ADD, SIDE_BID, px(97), 5, 4),
+                mbo(ACTION_ADD, SIDE_ASK, px(101), 5, 5),
+            ],
+        );
+        book
+    }
+
+    #[test]
+    fn deltas_insert_above_best_pushes_level_out_of_depth() {
+        let mut book = deltas_book();
+        let deltas = step(&mut book, &[mbo(ACTION_ADD, SIDE_BID, px(100) + 500_000_000, 1, 10)]);
+        assert_eq!(
+            actions(&deltas),
+            vec![(LevelAction::Delete, 2, px(98)), (LevelAction::New, 0, px(100) + 500_000_000)]
+        );
+    }
+
+    #[test]
+    fn deltas_delete_at_depth_pulls_next_level_in() {
+        let mut book = deltas_book();
+        let deltas = step(&mut book, &[mbo(ACTION_CANCEL, SIDE_BID, px(98), 5, 3)]);
+        assert_eq!(actions(&deltas), vec![(LevelAction::Delete, 2, px(98)), (LevelAction::New, 2, px(97))]);
+
+        // Deleting levels 0 and 1 in one event: deepest first, so indices stay valid
+        // and 97 moves up to level 0 without a delta of its own.
+        let deltas = step(
+            &mut book,
+            &[mbo(ACTION_CANCEL, SIDE_BID, px(100), 5, 1), mbo(ACTION_CANCEL, SIDE_BID, px(99), 5, 2)],
+        );
+        assert_eq!(
+            actions(&deltas),
+            vec![(LevelAction::Delete, 1, px(99)), (LevelAction::Delete, 0, px(100))]
+        );
+    }
+
+    #[test]
+    fn deltas_change_and_no_op() {
+        let mut book = deltas_book();
+        let deltas = step(&mut book, &[mbo(ACTION_CANCEL, SIDE_BID, px(99), 2, 2)]);
+        assert_eq!(actions(&deltas), vec![(LevelAction::Change, 1, px(99))]);
+        assert_eq!(deltas[0].size, 3);
+        // Changes below the tracked depth produce nothing.
+        assert!(step(&mut book, &[mbo(ACTION_CANCEL, SIDE_BID, px(97), 1, 4)]).is_empty());
+    }
+
+    #[test]
+    fn deltas_track_a_synthetic_stream() {
+        use crate::dbn::synth::{SynthConfig, SynthGenerator};
+        let mut book = Book::new();
+        let mut event = Vec::new();
+        let generator = SynthGenerator::new(SynthConfig { max_orders: 40, ..SynthConfig::default() });
+        for msg in generator.with_limit(20_000) {
+            event.push(msg);
+            if msg.is_last() {
+                step(&mut book, &event);
+                event.clear();
+            }
+        }
+    }
+
+    fn at(mut msg: MboMsg, ts_event: u64) -> MboMsg {
+        msg.hd.ts_event = ts_event;
+        msg
+    }
+
+    fn queue(level: &L3Level) -> Vec<(u64, u32, u64)> {
+        level.orders.iter().map(|o| (o.order_id, o.size, o.ts_event)).collect()
+    }
+
+    #[test]
+    fn partial_cancel_shrinks_the_level_and_keeps_queue_position() {
+        let mut book = Book::new();
+        book.apply(&at(mbo(ACTION_ADD, SIDE_BID, px(100), 5, 1), 10)).unwrap();
+        book.apply(&at(mbo(ACTION_ADD, SIDE_BID, px(100), 4, 2), 20)).unwrap();
+
+        book.apply(&mbo(ACTION_CANCEL, SIDE_BID, px(100), 2, 1)).unwrap();
+        let (bids, _) = book.get_l3(None);
+        assert_eq!((bids[0].size, bids[0].count), (7, 2));
+        assert_eq!(queue(&bids[0]), [(1, 3, 10), (2, 4, 20)]);
+        assert_eq!(book.bbo().0, Some(PriceLevel { price: Price::new(px(100)).unwrap(), size: 7, count: 2 }));
+
+        book.apply(&mbo(ACTION_CANCEL, SIDE_BID, px(100), 3, 1)).unwrap();
+        let (bids, _) = book.get_l3(None);
+        assert_eq!((bids[0].size, bids[0].count), (4, 1));
+        assert_eq!(queue(&bids[0]), [(2, 4, 20)]);
+    }
+
+    #[test]
+    fn l3_lists_orders_in_queue_order() {
+        let mut book = Book::new();
+        for (order_id, ts) in [(1, 10), (2, 20), (3, 30)] {
+            book.apply(&at(mbo(ACTION_ADD, SIDE_ASK, px(101), 1, order_id), ts)).unwrap();
+        }
+        let (_, asks) = book.get_l3(None);
+        assert_eq!(queue(&asks[0]), [(1, 1, 10), (2, 1, 20), (3, 1, 30)]);
+
+        // A modify loses priority: the order moves to the back with the modify's timestamp.
+        book.apply(&at(mbo(ACTION_MODIFY, SIDE_ASK, px(101), 2, 1), 40)).unwrap();
+        let (_, asks) = book.get_l3(None);
+        assert_eq!(queue(&asks[0]), [(2, 1, 20), (3, 1, 30), (1, 2, 40)]);
+    }
+
+    #[test]
+    fn l3_levels_limits_each_side_from_the_best_price() {
+        let mut book = Book::new();
+        for (i, units) in [97, 98, 99, 100].into_iter().enumerate() {
+            book.apply(&mbo(ACTION_ADD, SIDE_BID, px(units), 1, i as u64 + 1)).unwrap();
+            book.apply(&mbo(ACTION_ADD, SIDE_ASK, px(units + 4), 1, i as u64 + 11)).unwrap();
+        }
+        let prices = |levels: &[L3Level]| levels.iter().map(|l| l.price.raw()).collect::<Vec<_>>();
+
+        let (bids, asks) = book.get_l3(Some(2));
+        assert_eq!(prices(&bids), [px(100), px(99)]);
+        assert_eq!(prices(&asks), [px(101), px(102)]);
+
+        let (bids, asks) = book.get_l3(None);
+        assert_eq!((bids.len(), asks.len()), (4, 4));
+        let (bids, asks) = book.get_l3(Some(10));
+        assert_eq!((bids.len(), asks.len()), (4, 4));
+        let (bids, asks) = book.get_l3(Some(0));
+        assert!(bids.is_empty() && asks.is_empty());
+    }
+
+    #[test]
+    fn snapshot_pads_dense_and_trims_sparse_with_null_prices() {
+        let mut book = seeded_book();
+        book.apply(&mbo(ACTION_ADD, SIDE_BID, px(99), 2, 3)).unwrap();
+
+        let dense = book.get_snapshot(4, SnapshotLayout::Dense);
+        assert_eq!(dense.len(), 4);
+        assert_eq!((dense[1].bid_px.map(Price::raw), dense[1].ask_px), (Some(px(99)), None));
+        assert!(dense[2..].iter().all(|row| *row == BidAskPair::default()));
+
+        // Sparse keeps a row while either side still has a level there.
+        let sparse = book.get_snapshot(4, SnapshotLayout::Sparse);
+        assert_eq!(sparse, dense[..2]);
+        assert_eq!(book.get_snapshot(1, SnapshotLayout::Sparse), dense[..1]);
+        assert!(Book::new().get_snapshot(4, SnapshotLayout::Sparse).is_empty());
+
+        let json = serde_json::to_value(&sparse[1]).unwrap();
+        assert!(json["ask_px"].is_null());
+        assert_eq!((&json["ask_sz"], &json["ask_ct"]), (&serde_json::json!(0), &serde_json::json!(0)));
+    }
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use dbn::pretty::fmt_ts;
+use crate::types::msg::*;
+
+/// Wire format for MBO messages on the HTTP, WebSocket and TCP feeds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageFormat {
+    /// Raw struct fields: `c_char` codes, fixed-point prices, ns timestamps.
+    #[default]
+    Compact,
+    /// Named action/side/flags, decimal price strings and ISO-8601 timestamps.
+    Pretty,
+}
+
+/// Human-readable view of an `MboMsg`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PrettyMbo {
+    pub ts_recv: String,
+    pub ts_event: String,
+    pub rtype: u8,
+    pub publisher_id: u16,
+    pub instrument_id: u32,
+    pub action: String,
+    pub side: String,
+    /// Exact decimal with 9 places; `None` for `UNDEF_PRICE`.
+    pub price: Option<String>,
+    pub size: u32,
+    pub channel_id: u8,
+    pub order_id: u64,
+    pub flags: Vec<&'static str>,
+    pub ts_in_delta: i32,
+    pub sequence: u32,
+}
+
+const FLAG_NAMES: [(u8, &str); 7] = [
+    (dbn::flags::LAST, "last"),
+    (dbn::flags::TOB, "tob"),
+    (dbn::flags::SNAPSHOT, "snapshot"),
+    (dbn::flags::MBP, "mbp"),
+    (dbn::flags::BAD_TS_RECV, "bad_ts_recv"),
+    (dbn::flags::MAYBE_BAD_BOOK, "maybe_bad_book"),
+    (dbn::flags::PUBLISHER_SPECIFIC, "publisher_specific"),
+];
+
+pub fn flag_names(flags: u8) -> Vec<&'static str> {
+    FLAG_NAMES.iter().filter(|(bit, _)| flags & bit != 0).map(|(_, name)| *name).collect()
+}
+
+/// Unknown codes fall back to the raw character.
+fn action_name(msg: &MboMsg) -> String {
+    msg.action().map_or_else(|_| msg.action_char().to_string(), |a| a.as_str().to_string())
+}
+
+fn side_name(msg: &MboMsg) -> String {
+    msg.side().map_or_else(|_| msg.side_char().to_string(), |s| s.as_str().to_string())
+}
+
+impl From<&MboMsg> for PrettyMbo {
+    fn from(msg: &MboMsg) -> Self {
+        Self {
+            ts_recv: fmt_ts(msg.ts_recv),
+            ts_event: fmt_ts(msg.ts_event()),
+            rtype: msg.hd.rtype,
+            publisher_id: msg.publisher_id(),
+            instrument_id: msg.instrument_id(),
+            action: action_name(msg),
+            side: side_name(msg),
+            price: msg.px().map(|px| px.to_string()),
+            size: msg.size,
+            channel_id: msg.channel_id,
+            order_id: msg.order_id,
+            flags: flag_names(msg.flags),
+            ts_in_delta: msg.ts_in_delta,
+            sequence: msg.sequence,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(action: std::ffi::c_char, side: std::ffi::c_char, price: i64) -> MboMsg {
+        MboMsg {
+            hd: RecordHeader { rtype: 0xA0, publisher_id: 2, instrument_id: 7, ts_event: 1_700_000_000_123_456_789 },
+            order_id: 42,
+            price,
+            size: 3,
+            flags: FLAG_LAST | dbn::flags::TOB,
+            channel_id: 0,
+            action,
+            side,
+            ts_recv: 1_700_000_000_223_456_789,
+            ts_in_delta: 150,
+            sequence: 9,
+        }
+    }
+
+    #[test]
+    fn converts_codes_prices_timestamps_and_flags() {
+        let pretty = PrettyMbo::from(&msg(ACTION_ADD, SIDE_BID, 64_770_000_000));
+        let json = serde_json::to_value(&pretty).unwrap();
+        assert_eq!(json["action"], "add");
+        assert_eq!(json["side"], "bid");
+        assert_eq!(json["price"], "64.770000000");
+        assert_eq!(json["ts_event"], "2023-11-14T22:13:20.123456789Z");
+        assert_eq!(json["ts_recv"], "2023-11-14T22:13:20.223456789Z");
+        assert_eq!(json["flags"], serde_json::json!(["last", "tob"]));
+        assert_eq!((&json["order_id"], &json["sequence"], &json["size"]), (&42.into(), &9.into(), &3.into()));
+
+        let pretty = PrettyMbo::from(&msg(ACTION_TRADE, SIDE_ASK, -1_500_000_000));
+        assert_eq!((pretty.action.as_str(), pretty.side.as_str()), ("trade", "ask"));
+        assert_eq!(pretty.price.as_deref(), Some("-1.500000000"));
+    }
+
+    #[test]
+    fn undefined_price_is_null_and_unknown_codes_stay_raw() {
+        let json = serde_json::to_value(PrettyMbo::from(&msg(ACTION_CLEAR, SIDE_NONE, UNDEF_PRICE))).unwrap();
+        assert!(json["price"].is_null());
+        assert_eq!((&json["action"], &json["side"]), (&"clear".into(), &"none".into()));
+
+        let pretty = PrettyMbo::from(&msg(b'X' as std::ffi::c_char, b'Z' as std::ffi::c_char, 1));
+        assert_eq!((pretty.action.as_str(), pretty.side.as_str()), ("X", "Z"));
+    }
+}
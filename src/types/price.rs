@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::types::msg::{FIXED_PRICE_SCALE, UNDEF_PRICE};
+
+/// Number of decimal places in the fixed-point representation.
+pub const PRICE_DECIMALS: usize = 9;
+
+/// A defined fixed-point price in units of 1e-9.
+///
+/// `UNDEF_PRICE` is never a valid `Price`: undefined prices are `Option<Price>`,
+/// built with `Price::new`. Serializes as the raw `i64`, like the DBN field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Price(i64);
+
+impl Price {
+    pub const ZERO: Price = Price(0);
+
+    /// `None` for `UNDEF_PRICE`.
+    pub const fn new(raw: i64) -> Option<Self> {
+        if raw == UNDEF_PRICE {
+            None
+        } else {
+            Some(Price(raw))
+        }
+    }
+
+    /// Raw fixed-point value, as stored in DBN records.
+    pub const fn raw(self) -> i64 {
+        self.0
+    }
+
+    /// Lossy conversion for analytics and plotting.
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / FIXED_PRICE_SCALE
+    }
+
+    /// Tick size of an instrument definition; `None` when the definition has no
+    /// positive `min_price_increment`.
+    pub fn tick_size(def: &dbn::record::InstrumentDefMsg) -> Option<Price> {
+        Price::new(def.min_price_increment).filter(|tick| tick.0 > 0)
+    }
+
+    // ============ CHECKED ARITHMETIC ============
+
+    /// `None` on overflow or if the result would be the undefined sentinel.
+    pub fn checked_add(self, rhs: Price) -> Option<Price> {
+        self.0.checked_add(rhs.0).and_then(Price::new)
+    }
+
+    pub fn checked_sub(self, rhs: Price) -> Option<Price> {
+        self.0.checked_sub(rhs.0).and_then(Price::new)
+    }
+
+    /// Price times an integer quantity, e.g. a notional in fixed-point units.
+    pub fn checked_mul(self, qty: i64) -> Option<Price> {
+        self.0.checked_mul(qty).and_then(Price::new)
+    }
+
+    /// Moves the price by `ticks` (negative moves down).
+    pub fn checked_add_ticks(self, ticks: i64, tick: Price) -> Option<Price> {
+        tick.checked_mul(ticks).and_then(|delta| self.checked_add(delta))
+    }
+
+    // ============ TICK ROUNDING ============
+
+    pub fn is_on_tick(self, tick: Price) -> bool {
+        tick.0 > 0 && self.0 % tick.0 == 0
+    }
+
+    /// Rounds down to a multiple of `tick`; `None` for a non-positive tick or overflow.
+    pub fn floor_to_tick(self, tick: Price) -> Option<Price> {
+        (tick.0 > 0).then(|| self.0.div_euclid(tick.0)).and_then(|n| tick.checked_mul(n))
+    }
+
+    /// Rounds up to a multiple of `tick`.
+    pub fn ceil_to_tick(self, tick: Price) -> Option<Price> {
+        let floor = self.floor_to_tick(tick)?;
+        if floor == self {
+            Some(floor)
+        } else {
+            floor.checked_add(tick)
+        }
+    }
+
+    /// Rounds to the nearest multiple of `tick`, halves away from zero.
+    pub fn round_to_tick(self, tick: Price) -> Option<Price> {
+        let floor = self.floor_to_tick(tick)?;
+        let rem = self.0 - floor.0;
+        let round_up = if self.0 >= 0 { rem >= tick.0 - rem } else { rem > tick.0 - rem };
+        if round_up {
+            floor.checked_add(tick)
+        } else {
+            Some(floor)
+        }
+    }
+}
+
+/// Exact decimal without going through `f64`. Prints all nine places by
+/// default; `{:.2}` rounds half away from zero (at most nine places), and
+/// width/fill/alignment apply.
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let places = f.precision().unwrap_or(PRICE_DECIMALS).min(PRICE_DECIMALS);
+        let abs = self.0.unsigned_abs();
+        let divisor = 10u64.pow((PRICE_DECIMALS - places) as u32);
+        let rounded = abs / divisor + u64::from(divisor > 1 && abs % divisor >= divisor / 2);
+        let unit = 10u64.pow(places as u32);
+        let (int, frac) = (rounded / unit, rounded % unit);
+        let digits = if places == 0 {
+            int.to_string()
+        } else {
+            format!("{}.{:0width$}", int, frac, width = places)
+        };
+        f.pad_integral(self.0 >= 0 || rounded == 0, "", &digits)
+    }
+}
+
+impl From<Price> for i64 {
+    fn from(price: Price) -> Self {
+        price.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_is_exact() {
+        assert_eq!(Price(64_770_000_000).to_string(), "64.770000000");
+        assert_eq!(Price(1).to_string(), "0.000000001");
+        assert_eq!(Price(-1).to_string(), "-0.000000001");
+        assert_eq!(Price(-1_500_000_000).to_string(), "-1.500000000");
+        assert_eq!(Price(0).to_string(), "0.000000000");
+        assert_eq!(Price(i64::MIN).to_string(), "-9223372036.854775808");
+        assert_eq!(Price(UNDEF_PRICE - 1).to_string(), "9223372036.854775806");
+    }
+
+    #[test]
+    fn display_precision_rounds_half_away_from_zero() {
+        assert_eq!(format!("{:.2}", Price(1_005_000_000)), "1.01");
+        assert_eq!(format!("{:.2}", Price(1_004_999_999)), "1.00");
+        assert_eq!(format!("{:.2}", Price(-1_005_000_000)), "-1.01");
+        assert_eq!(format!("{:.0}", Price(2_500_000_000)), "3");
+        // A negative that rounds to zero prints without a sign.
+        assert_eq!(format!("{:.2}", Price(-4_000_000)), "0.00");
+        assert_eq!(format!("{:.12}", Price(1)), "0.000000001");
+        assert_eq!(format!("{:>8.2}", Price(64_770_000_000)), "   64.77");
+    }
+
+    #[test]
+    fn undefined_price_is_none() {
+        assert_eq!(Price::new(UNDEF_PRICE), None);
+        assert_eq!(Price::new(0), Some(Price::ZERO));
+        assert_eq!(Price::new(-5).map(Price::raw), Some(-5));
+    }
+
+    #[test]
+    fn checked_arithmetic_overflows_to_none() {
+        let max = Price(UNDEF_PRICE - 1);
+        // i64::MAX is the undefined sentinel, not a price.
+        assert_eq!(max.checked_add(Price(1)), None);
+        assert_eq!(max.checked_add(Price(2)), None);
+        assert_eq!(Price(i64::MIN).checked_sub(Price(1)), None);
+        assert_eq!(Price(i64::MAX / 2).checked_mul(3), None);
+        assert_eq!(Price(10).checked_add_ticks(i64::MAX, Price(2)), None);
+        assert_eq!(Price(10).checked_add_ticks(-3, Price(2)), Some(Price(4)));
+        assert_eq!(Price(7).checked_mul(-2), Some(Price(-14)));
+    }
+
+    #[test]
+    fn tick_rounding_at_boundaries() {
+        let tick = Price(250);
+        for (px, floor, ceil, round) in [
+            (1000, 1000, 1000, 1000),
+            (1001, 1000, 1250, 1000),
+            (1124, 1000, 1250, 1000),
+            (1125, 1000, 1250, 1250),
+            (1249, 1000, 1250, 1250),
+            (-1001, -1250, -1000, -1000),
+            (-1124, -1250, -1000, -1000),
+            (-1125, -1250, -1000, -1250),
+            (0, 0, 0, 0),
+        ] {
+            let px = Price(px);
+            assert_eq!(px.floor_to_tick(tick), Some(Price(floor)), "floor {}", px.raw());
+            assert_eq!(px.ceil_to_tick(tick), Some(Price(ceil)), "ceil {}", px.raw());
+            assert_eq!(px.round_to_tick(tick), Some(Price(round)), "round {}", px.raw());
+        }
+        assert!(Price(1000).is_on_tick(tick));
+        assert!(Price(-750).is_on_tick(tick));
+        assert!(!Price(1001).is_on_tick(tick));
+    }
+
+    #[test]
+    fn tick_rounding_rejects_bad_ticks_and_overflow() {
+        assert_eq!(Price(1000).floor_to_tick(Price::ZERO), None);
+        assert_eq!(Price(1000).round_to_tick(Price(-5)), None);
+        assert!(!Price(1000).is_on_tick(Price::ZERO));
+        assert_eq!(Price(UNDEF_PRICE - 1).ceil_to_tick(Price(10)), None);
+    }
+}